@@ -7,7 +7,7 @@ use std::time::Duration;
 fn main() {
     custom_utils::logger::logger_stdout_debug();
     let config = CollectParam {
-        address: Ipv4Addr::new(192, 168, 254, 60).into(),
+        address: Ipv4Addr::new(192, 168, 254, 60),
         port: 102,
         collect_mode: CollectMode::RackSlot {
             conn_type: Default::default(),
@@ -72,10 +72,23 @@ fn main() {
             )
         );
     }
+    {
+        // 读MW100数据
+        debug!(
+            "{}",
+            simple_hex(
+                &cl.read(Area::Merker(DataSizeType::Word { addr: 100, len: 1 }))
+                    .unwrap()
+            )
+        );
+    }
     // 写V300的long值
     // let val = 160u32;
-    // cl.ag_write(1, 300, 4, &mut val.to_be_bytes().as_slice().to_vec())
-    //     .unwrap();
+    // cl.write(
+    //     Area::V(DataSizeType::DWord { addr: 300, len: 1 }),
+    //     &val.to_be_bytes(),
+    // )
+    // .unwrap();
     // 读V300的long值
     // let buffer = &mut vec![0u8; 1];
     // cl.ag_read(1, 300, 1, buffer).unwrap();
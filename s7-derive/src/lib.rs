@@ -0,0 +1,464 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! `#[derive(S7Layout)]` for the `s7` crate, enable it with the `derive` feature of `s7`
+//!
+//! The offsets of the members follow the rules of standard (non-optimized) data blocks:
+//! bools are packed into bytes, `u8`, `i8` and `char` start at the next whole byte and every other
+//! type at the next even byte. The size of the struct is rounded up to an even number of bytes.
+//!
+//! | Rust     | S7                      | field type         |
+//! |----------|-------------------------|--------------------|
+//! | `bool`   | BOOL                    | `field::Bool`      |
+//! | `u8`     | BYTE                    | `field::Byte`      |
+//! | `i8`     | SINT                    | `field::SInt`      |
+//! | `char`   | CHAR                    | `field::Char`      |
+//! | `u16`    | WORD                    | `field::Word`      |
+//! | `i16`    | INT                     | `field::Int`       |
+//! | `u32`    | DWORD                   | `field::DWord`     |
+//! | `i32`    | DINT                    | `field::DInt`      |
+//! | `f32`    | REAL                    | `field::Float`     |
+//! | `f64`    | LREAL                   | `field::LReal`     |
+//! | `i64`    | LINT                    | `field::LInt`      |
+//! | `u64`    | ULINT                   | `field::ULInt`     |
+//! | `String` | STRING[254]             | `field::S7String`  |
+//!
+//! Attributes:
+//! - `#[s7(db = 10)]` or `#[s7(db = 10, offset = 100)]` on the struct adds `area()`
+//!   returning the area of the whole struct
+//! - `#[s7(offset = "12.3")]` on a member places it at byte 12 bit 3, the following members
+//!   continue after it. Members wider than a byte need an even offset and members may not
+//!   overlap.
+//! - `#[s7(string_len = 20)]` on a `String` member makes it a STRING[20], the length is 1 to 254
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitInt, LitStr, Type};
+
+#[proc_macro_derive(S7Layout, attributes(s7))]
+pub fn derive_s7_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// how a member is aligned and converted
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    /// packed into the current byte
+    Bit,
+    /// starts at the next whole byte
+    Byte,
+    /// starts at the next even byte
+    Word,
+}
+
+/// PLC representation of a member type
+struct Member {
+    /// type in `s7::field`
+    field: &'static str,
+    kind: Kind,
+    size: u32,
+    /// `set_value` of the field type returns a `Result`
+    fallible: bool,
+    /// max length of a STRING
+    string_len: Option<u8>,
+}
+
+impl Member {
+    fn of(ty: &Type, string_len: Option<u8>) -> syn::Result<Member> {
+        let name = match ty {
+            Type::Path(path) if path.qself.is_none() => path
+                .path
+                .segments
+                .last()
+                .map(|segment| segment.ident.to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        let (field, kind, size, fallible) = match name.as_str() {
+            "bool" => ("Bool", Kind::Bit, 1, false),
+            "u8" => ("Byte", Kind::Byte, 1, false),
+            "i8" => ("SInt", Kind::Byte, 1, false),
+            "char" => ("Char", Kind::Byte, 1, true),
+            "u16" => ("Word", Kind::Word, 2, false),
+            "i16" => ("Int", Kind::Word, 2, false),
+            "u32" => ("DWord", Kind::Word, 4, false),
+            "i32" => ("DInt", Kind::Word, 4, false),
+            "f32" => ("Float", Kind::Word, 4, false),
+            "f64" => ("LReal", Kind::Word, 8, false),
+            "i64" => ("LInt", Kind::Word, 8, false),
+            "u64" => ("ULInt", Kind::Word, 8, false),
+            "String" => {
+                let len = string_len.unwrap_or(254);
+                return Ok(Member {
+                    field: "S7String",
+                    kind: Kind::Word,
+                    size: len as u32 + 2,
+                    fallible: true,
+                    string_len: Some(len),
+                });
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "S7Layout: unsupported type, expected bool, u8, i8, char, u16, i16, u32, i32, f32, f64, i64, u64 or String",
+                ))
+            }
+        };
+        if string_len.is_some() {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "S7Layout: string_len is only allowed on String members",
+            ));
+        }
+        Ok(Member {
+            field,
+            kind,
+            size,
+            fallible,
+            string_len: None,
+        })
+    }
+}
+
+/// computes the offsets of the members in declaration order
+#[derive(Debug, Default)]
+struct Layout {
+    byte: u32,
+    bit: u8,
+    /// end of the last byte used by any member
+    end: u32,
+    /// bits used by the members placed so far, the end is exclusive
+    used: Vec<(u32, u32)>,
+}
+
+impl Layout {
+    /// places a member at the next free position or at `at`, returns its byte and bit offset
+    fn place(&mut self, kind: Kind, size: u32, at: Option<(u32, u8)>) -> (u32, u8) {
+        match at {
+            Some((byte, bit)) => {
+                self.byte = byte;
+                self.bit = bit;
+            }
+            None => {
+                if kind != Kind::Bit && self.bit > 0 {
+                    self.byte += 1;
+                    self.bit = 0;
+                }
+                if kind == Kind::Word && self.byte % 2 == 1 {
+                    self.byte += 1;
+                }
+            }
+        }
+        let offset = (self.byte, self.bit);
+        let start = self.byte * 8 + self.bit as u32;
+        let bits = if kind == Kind::Bit { 1 } else { size * 8 };
+        self.used.push((start, start + bits));
+        if kind == Kind::Bit {
+            self.end = self.end.max(self.byte + 1);
+            self.bit += 1;
+            if self.bit == 8 {
+                self.byte += 1;
+                self.bit = 0;
+            }
+        } else {
+            self.byte += size;
+            self.end = self.end.max(self.byte);
+        }
+        offset
+    }
+
+    /// whether the member placed last overlaps an earlier member
+    fn overlaps(&self) -> bool {
+        match self.used.split_last() {
+            Some(((start, end), earlier)) => earlier.iter().any(|(s, e)| s < end && start < e),
+            None => false,
+        }
+    }
+
+    /// size of the struct, rounded up to an even number of bytes
+    fn size(&self) -> u32 {
+        self.end + self.end % 2
+    }
+}
+
+/// `#[s7(...)]` arguments of the struct or a member
+#[derive(Default)]
+struct Args {
+    db: Option<u16>,
+    offset: Option<(u32, u8)>,
+    string_len: Option<u8>,
+}
+
+impl Args {
+    fn parse(attrs: &[syn::Attribute], on_struct: bool) -> syn::Result<Args> {
+        let mut args = Args::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("s7")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("db") && on_struct {
+                    args.db = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                } else if meta.path.is_ident("offset") && on_struct {
+                    let offset: u32 = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+                    args.offset = Some((offset, 0));
+                } else if meta.path.is_ident("offset") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    args.offset = Some(parse_offset(&lit)?);
+                } else if meta.path.is_ident("string_len") && !on_struct {
+                    let lit: LitInt = meta.value()?.parse()?;
+                    args.string_len = Some(parse_string_len(&lit)?);
+                } else {
+                    return Err(meta.error("S7Layout: unsupported attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(args)
+    }
+}
+
+/// STRING lengths from 1 to 254
+fn parse_string_len(lit: &LitInt) -> syn::Result<u8> {
+    match lit.base10_parse::<u32>()? {
+        len @ 1..=254 => Ok(len as u8),
+        _ => Err(syn::Error::new_spanned(
+            lit,
+            "S7Layout: string_len has to be between 1 and 254",
+        )),
+    }
+}
+
+/// parses `"12"` or `"12.3"` to byte and bit
+fn parse_offset(lit: &LitStr) -> syn::Result<(u32, u8)> {
+    let value = lit.value();
+    let mut parts = value.splitn(2, '.');
+    let byte = parts.next().unwrap_or_default().parse::<u32>();
+    let bit = parts.next().map(str::parse::<u8>).unwrap_or(Ok(0));
+    match (byte, bit) {
+        (Ok(byte), Ok(bit)) if bit < 8 => Ok((byte, bit)),
+        _ => Err(syn::Error::new_spanned(
+            lit,
+            "S7Layout: expected an offset like \"12\" or \"12.3\"",
+        )),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    input,
+                    "S7Layout: only structs with named fields are supported",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "S7Layout: only structs are supported",
+            ))
+        }
+    };
+    let struct_args = Args::parse(&input.attrs, true)?;
+
+    let mut layout = Layout::default();
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let args = Args::parse(&field.attrs, false)?;
+        let member = Member::of(&field.ty, args.string_len)?;
+        if let Some((_, bit)) = args.offset {
+            if bit != 0 && member.kind != Kind::Bit {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "S7Layout: only bool members can have a bit offset",
+                ));
+            }
+        }
+        if let Some((byte, _)) = args.offset {
+            if byte % 2 == 1 && member.kind == Kind::Word {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "S7Layout: only bool, u8, i8 and char members can start at an odd byte",
+                ));
+            }
+        }
+        let (byte, bit) = layout.place(member.kind, member.size, args.offset);
+        if layout.overlaps() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "S7Layout: the member overlaps an earlier member",
+            ));
+        }
+        let (start, end) = (byte as usize, (byte + member.size) as usize);
+        let field_type = Ident::new(member.field, Span::call_site());
+        let field_type = quote!(::s7::field::#field_type);
+
+        if member.kind == Kind::Bit {
+            let bit = bit as u16;
+            reads.push(quote! {
+                #ident: #field_type::new(
+                    ::s7::layout::member_bit_area(#byte, #bit)?,
+                    bytes[#start..#end].to_vec(),
+                )?
+                .value()
+            });
+            writes.push(quote! {
+                let mut field = #field_type::new(
+                    ::s7::layout::member_bit_area(#byte, #bit)?,
+                    buf[#start..#end].to_vec(),
+                )?;
+                field.set_value(self.#ident);
+                buf[#start..#end].copy_from_slice(&::s7::field::Field::to_bytes(&field));
+            });
+            continue;
+        }
+
+        if let Some(len) = member.string_len {
+            reads.push(quote! {
+                #ident: #field_type::new(
+                    ::s7::layout::member_area(#byte),
+                    bytes[#start..#end].to_vec(),
+                )?
+                .value()
+                .to_string()
+            });
+            writes.push(quote! {
+                buf[#start] = #len;
+                let mut field = #field_type::new(
+                    ::s7::layout::member_area(#byte),
+                    buf[#start..#end].to_vec(),
+                )?;
+                field.set_value(&self.#ident)?;
+                buf[#start..#end].copy_from_slice(&::s7::field::Field::to_bytes(&field));
+            });
+            continue;
+        }
+
+        reads.push(quote! {
+            #ident: #field_type::new(
+                ::s7::layout::member_area(#byte),
+                bytes[#start..#end].to_vec(),
+            )?
+            .value()
+        });
+        let set_value = if member.fallible {
+            quote!(field.set_value(self.#ident)?;)
+        } else {
+            quote!(field.set_value(self.#ident);)
+        };
+        writes.push(quote! {
+            let mut field = #field_type::new(
+                ::s7::layout::member_area(#byte),
+                buf[#start..#end].to_vec(),
+            )?;
+            #set_value
+            buf[#start..#end].copy_from_slice(&::s7::field::Field::to_bytes(&field));
+        });
+    }
+
+    let size = layout.size() as usize;
+    let name_str = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let area = struct_args.db.map(|db| {
+        let (offset, _) = struct_args.offset.unwrap_or((0, 0));
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// area covering the whole struct
+                pub fn area() -> ::s7::Area {
+                    <Self as ::s7::layout::S7Layout>::area_at(#db, #offset)
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::s7::layout::S7Layout for #name #ty_generics #where_clause {
+            const SIZE: usize = #size;
+
+            fn from_bytes(bytes: &[u8]) -> ::std::result::Result<Self, ::s7::error::Error> {
+                ::s7::layout::check_size(#name_str, #size, bytes)?;
+                Ok(#name {
+                    #(#reads,)*
+                })
+            }
+
+            fn to_bytes(&self) -> ::std::result::Result<::std::vec::Vec<u8>, ::s7::error::Error> {
+                let mut buf = vec![0u8; #size];
+                #(#writes)*
+                Ok(buf)
+            }
+        }
+
+        #area
+    })
+}
+
+#[test]
+fn test_layout() {
+    let mut layout = Layout::default();
+    assert_eq!(layout.place(Kind::Bit, 1, None), (0, 0));
+    assert_eq!(layout.place(Kind::Bit, 1, None), (0, 1));
+    // bytes start at the next whole byte
+    assert_eq!(layout.place(Kind::Byte, 1, None), (1, 0));
+    assert_eq!(layout.place(Kind::Byte, 1, None), (2, 0));
+    assert_eq!(layout.place(Kind::Bit, 1, None), (3, 0));
+    // everything else at the next even byte
+    assert_eq!(layout.place(Kind::Word, 2, None), (4, 0));
+    assert_eq!(layout.place(Kind::Byte, 1, None), (6, 0));
+    assert_eq!(layout.place(Kind::Word, 22, None), (8, 0));
+    assert_eq!(layout.size(), 30);
+    assert_eq!(layout.place(Kind::Byte, 1, None), (30, 0));
+    assert_eq!(layout.size(), 32);
+
+    // eight bools fill exactly one byte
+    let mut layout = Layout::default();
+    for bit in 0..8 {
+        assert_eq!(layout.place(Kind::Bit, 1, None), (0, bit));
+    }
+    assert_eq!(layout.place(Kind::Bit, 1, None), (1, 0));
+    assert_eq!(layout.size(), 2);
+}
+
+#[test]
+fn test_layout_offset() {
+    let mut layout = Layout::default();
+    assert_eq!(layout.place(Kind::Word, 4, Some((12, 0))), (12, 0));
+    assert_eq!(layout.place(Kind::Bit, 1, Some((4, 3))), (4, 3));
+    assert_eq!(layout.place(Kind::Bit, 1, None), (4, 4));
+    assert_eq!(layout.place(Kind::Word, 2, None), (6, 0));
+    // the size covers the member placed furthest
+    assert_eq!(layout.size(), 16);
+    assert!(!layout.overlaps());
+    layout.place(Kind::Word, 2, Some((14, 0)));
+    assert!(layout.overlaps());
+
+    let mut layout = Layout::default();
+    layout.place(Kind::Bit, 1, Some((0, 3)));
+    layout.place(Kind::Bit, 1, Some((0, 4)));
+    assert!(!layout.overlaps());
+    layout.place(Kind::Byte, 1, Some((0, 0)));
+    assert!(layout.overlaps());
+
+    let string_len = |s: &str| parse_string_len(&LitInt::new(s, Span::call_site()));
+    assert_eq!(string_len("1").unwrap(), 1);
+    assert_eq!(string_len("254").unwrap(), 254);
+    assert!(string_len("0").is_err());
+    assert!(string_len("255").is_err());
+
+    let offset = |s: &str| parse_offset(&LitStr::new(s, Span::call_site()));
+    assert_eq!(offset("12.3").unwrap(), (12, 3));
+    assert_eq!(offset("12").unwrap(), (12, 0));
+    assert!(offset("12.8").is_err());
+    assert!(offset("a.1").is_err());
+}
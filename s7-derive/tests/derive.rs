@@ -0,0 +1,61 @@
+use s7::{Area, DataSizeType, S7Layout};
+
+#[derive(Debug, PartialEq, S7Layout)]
+#[s7(db = 10, offset = 100)]
+struct Recipe {
+    active: bool,
+    heating: bool,
+    step: u8,
+    speed: i16,
+    #[s7(string_len = 4)]
+    name: String,
+    letter: char,
+    trim: i8,
+    temperature: f32,
+    #[s7(offset = "30.3")]
+    alarm: bool,
+    count: u64,
+}
+
+#[test]
+fn test_derive_layout() {
+    assert_eq!(Recipe::SIZE, 40);
+    match Recipe::area() {
+        Area::DataBausteine(10, DataSizeType::Byte { addr: 100, len: 40 }) => {}
+        area => panic!("unexpected area {:?}", area),
+    }
+
+    let recipe = Recipe {
+        active: true,
+        heating: false,
+        step: 7,
+        speed: -2,
+        name: "ab".to_string(),
+        letter: 'x',
+        trim: -3,
+        temperature: 53.5,
+        alarm: true,
+        count: 1,
+    };
+    let bytes = recipe.to_bytes().unwrap();
+    let mut expected = vec![0u8; 40];
+    expected[0] = 0b01;
+    expected[1] = 7;
+    expected[2..4].copy_from_slice(&[0xFF, 0xFE]);
+    expected[4..8].copy_from_slice(&[4, 2, b'a', b'b']);
+    expected[10] = b'x';
+    expected[11] = 0xFD;
+    expected[12..16].copy_from_slice(&[66, 86, 0, 0]);
+    expected[30] = 0b1000;
+    expected[32..40].copy_from_slice(&1u64.to_be_bytes());
+    assert_eq!(bytes, expected);
+
+    assert_eq!(Recipe::from_bytes(&bytes).unwrap(), recipe);
+    assert!(Recipe::from_bytes(&bytes[1..]).is_err());
+
+    let too_long = Recipe {
+        name: "abcde".to_string(),
+        ..recipe
+    };
+    assert!(too_long.to_bytes().is_err());
+}
@@ -0,0 +1,459 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Step7 address syntax for `Area`
+//!
+//! Single values use the classic notation, ie `DB10.DBX4.3`, `DB1.DBW20`, `MW100`, `I0.1`,
+//! `QB2`, `PIW256`, `T5`, `C3` or `VD300`. German mnemonics (`E`, `A`, `PE`, `PA`, `Z`) are
+//! accepted as well. Peripheral inputs and outputs are `PI` and `PQ`, ie `PIW256` and `PQB4`.
+//!
+//! Areas which are not a single value, ie typed or longer areas, use the ANY pointer
+//! notation like `P#DB1.DBX20.0 INT 5`, which is parsed back to the same area.
+//!
+//! `DI` is used for instance data blocks, `L` for local data and `VL` for the local data
+//! of the calling block.
+
+use crate::constant::{Area, BitAddr, DataSizeType};
+use crate::error::Error;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+impl FromStr for Area {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim().to_ascii_uppercase();
+        let area = match input.strip_prefix("P#") {
+            Some(pointer) => parse_pointer(pointer)?,
+            None => parse_single(&input)?,
+        };
+        area.ok_or_else(|| Error::InvalidInput {
+            input: format!("invalid address {}", s),
+        })
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use DataSizeType::*;
+        let prefix = Prefix::of(self);
+        match **self {
+            Bit { addr, bit_addr } => write!(
+                f,
+                "{}{}{}.{}",
+                prefix,
+                prefix.bit_prefix(),
+                addr,
+                bit_addr as u8
+            ),
+            Byte { addr, len: 1 } => write!(f, "{}B{}", prefix, addr),
+            Word { addr, len: 1 } => write!(f, "{}W{}", prefix, addr),
+            DWord { addr, len: 1 } => write!(f, "{}D{}", prefix, addr),
+            Counter { addr, len: 1 } | Timer { addr, len: 1 } => write!(f, "{}{}", prefix, addr),
+            Counter { addr, len } | Timer { addr, len } => {
+                write!(f, "P#{}{} {} {}", prefix, addr, type_name(self), len)
+            }
+            _ => write!(
+                f,
+                "P#{}{}{}.0 {} {}",
+                prefix,
+                prefix.bit_prefix(),
+                self.start(),
+                type_name(self),
+                self.len()
+            ),
+        }
+    }
+}
+
+/// area part of an address, ie `DB10.DB` of `DB10.DBW4` or `M` of `MW100`
+#[derive(Debug, Copy, Clone)]
+enum Prefix {
+    ProcessInput,
+    ProcessOutput,
+    Merker,
+    V,
+    DataBausteine(u16),
+    PeripheralInput,
+    PeripheralOutput,
+    InstanceData(u16),
+    LocalData,
+    PreviousLocalData,
+    Counter,
+    Timer,
+}
+
+impl Prefix {
+    fn of(area: &Area) -> Prefix {
+        match area {
+            Area::ProcessInput(_) => Prefix::ProcessInput,
+            Area::ProcessOutput(_) => Prefix::ProcessOutput,
+            Area::Merker(_) => Prefix::Merker,
+            Area::V(_) => Prefix::V,
+            Area::DataBausteine(db, _) => Prefix::DataBausteine(*db),
+            Area::PeripheralInput(_) => Prefix::PeripheralInput,
+            Area::PeripheralOutput(_) => Prefix::PeripheralOutput,
+            Area::InstanceData(db, _) => Prefix::InstanceData(*db),
+            Area::LocalData(_) => Prefix::LocalData,
+            Area::PreviousLocalData(_) => Prefix::PreviousLocalData,
+            Area::Counter(_) => Prefix::Counter,
+            Area::Timer(_) => Prefix::Timer,
+        }
+    }
+
+    fn area(self, data: DataSizeType) -> Area {
+        match self {
+            Prefix::ProcessInput => Area::ProcessInput(data),
+            Prefix::ProcessOutput => Area::ProcessOutput(data),
+            Prefix::Merker => Area::Merker(data),
+            Prefix::V => Area::V(data),
+            Prefix::DataBausteine(db) => Area::DataBausteine(db, data),
+            Prefix::PeripheralInput => Area::PeripheralInput(data),
+            Prefix::PeripheralOutput => Area::PeripheralOutput(data),
+            Prefix::InstanceData(db) => Area::InstanceData(db, data),
+            Prefix::LocalData => Area::LocalData(data),
+            Prefix::PreviousLocalData => Area::PreviousLocalData(data),
+            Prefix::Counter => Area::Counter(data),
+            Prefix::Timer => Area::Timer(data),
+        }
+    }
+
+    /// bits of data blocks need the `X` size suffix, ie `DB1.DBX0.1`, everything else has none
+    fn bit_prefix(self) -> &'static str {
+        match self {
+            Prefix::DataBausteine(_) | Prefix::InstanceData(_) => "X",
+            _ => "",
+        }
+    }
+
+    /// counters and timers are addressed by number instead of byte.bit
+    fn numbered(self) -> bool {
+        matches!(self, Prefix::Counter | Prefix::Timer)
+    }
+
+    /// splits the area from the rest of the address, ie `MW100` is split to `Merker` and `W100`
+    fn split(input: &str) -> Option<(Prefix, &str)> {
+        for block in ["DB", "DI"] {
+            if let Some(rest) = input.strip_prefix(block) {
+                let (number, rest) = rest.split_once('.')?;
+                let number = number.parse::<u16>().ok()?;
+                let rest = rest.strip_prefix(block)?;
+                let prefix = if block == "DB" {
+                    Prefix::DataBausteine(number)
+                } else {
+                    Prefix::InstanceData(number)
+                };
+                return Some((prefix, rest));
+            }
+        }
+        const PREFIXES: [(&str, Prefix); 15] = [
+            ("PI", Prefix::PeripheralInput),
+            ("PE", Prefix::PeripheralInput),
+            ("PQ", Prefix::PeripheralOutput),
+            ("PA", Prefix::PeripheralOutput),
+            ("I", Prefix::ProcessInput),
+            ("E", Prefix::ProcessInput),
+            ("Q", Prefix::ProcessOutput),
+            ("A", Prefix::ProcessOutput),
+            ("M", Prefix::Merker),
+            ("VL", Prefix::PreviousLocalData),
+            ("V", Prefix::V),
+            ("L", Prefix::LocalData),
+            ("T", Prefix::Timer),
+            ("C", Prefix::Counter),
+            ("Z", Prefix::Counter),
+        ];
+        PREFIXES
+            .iter()
+            .find_map(|(name, prefix)| input.strip_prefix(name).map(|rest| (*prefix, rest)))
+    }
+}
+
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Prefix::ProcessInput => write!(f, "I"),
+            Prefix::ProcessOutput => write!(f, "Q"),
+            Prefix::Merker => write!(f, "M"),
+            Prefix::V => write!(f, "V"),
+            Prefix::DataBausteine(db) => write!(f, "DB{}.DB", db),
+            Prefix::PeripheralInput => write!(f, "PI"),
+            Prefix::PeripheralOutput => write!(f, "PQ"),
+            Prefix::InstanceData(db) => write!(f, "DI{}.DI", db),
+            Prefix::LocalData => write!(f, "L"),
+            Prefix::PreviousLocalData => write!(f, "VL"),
+            Prefix::Counter => write!(f, "C"),
+            Prefix::Timer => write!(f, "T"),
+        }
+    }
+}
+
+/// ANY pointer data type of the area
+fn type_name(data: &DataSizeType) -> &'static str {
+    use DataSizeType::*;
+    match data {
+        Bit { .. } => "BOOL",
+        Byte { .. } => "BYTE",
+        Char { .. } => "CHAR",
+        Word { .. } => "WORD",
+        Int { .. } => "INT",
+        DWord { .. } => "DWORD",
+        DInt { .. } => "DINT",
+        Real { .. } => "REAL",
+        Counter { .. } => "COUNTER",
+        Timer { .. } => "TIMER",
+    }
+}
+
+/// parses `byte.bit`, the bit is validated with `BitAddr::try_from`
+fn parse_bit(input: &str) -> Result<Option<(u32, BitAddr)>, Error> {
+    let (byte, bit) = match input.split_once('.') {
+        Some(parts) => parts,
+        None => return Ok(None),
+    };
+    match (byte.parse::<u32>(), bit.parse::<u16>()) {
+        (Ok(byte), Ok(bit)) => Ok(Some((byte, BitAddr::try_from(bit)?))),
+        _ => Ok(None),
+    }
+}
+
+/// parses a single value, ie `DB10.DBX4.3` or `MW100`
+fn parse_single(input: &str) -> Result<Option<Area>, Error> {
+    let (prefix, rest) = match Prefix::split(input) {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+    if prefix.numbered() {
+        return Ok(rest.parse::<u32>().ok().map(|addr| {
+            prefix.area(match prefix {
+                Prefix::Counter => DataSizeType::Counter { addr, len: 1 },
+                _ => DataSizeType::Timer { addr, len: 1 },
+            })
+        }));
+    }
+
+    let size = rest.chars().next();
+    let data = match size {
+        Some('B') | Some('W') | Some('D') => match rest[1..].parse::<u32>() {
+            Ok(addr) => match size {
+                Some('B') => DataSizeType::Byte { addr, len: 1 },
+                Some('W') => DataSizeType::Word { addr, len: 1 },
+                _ => DataSizeType::DWord { addr, len: 1 },
+            },
+            Err(_) => return Ok(None),
+        },
+        _ => {
+            let bit = match rest.strip_prefix('X') {
+                Some(bit) => bit,
+                None if prefix.bit_prefix().is_empty() => rest,
+                None => return Ok(None),
+            };
+            match parse_bit(bit)? {
+                Some((addr, bit_addr)) => DataSizeType::Bit { addr, bit_addr },
+                None => return Ok(None),
+            }
+        }
+    };
+    Ok(Some(prefix.area(data)))
+}
+
+/// parses an ANY pointer without the `P#`, ie `DB1.DBX20.0 INT 5`
+fn parse_pointer(input: &str) -> Result<Option<Area>, Error> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let (address, type_name, len) = match parts.as_slice() {
+        [address, type_name, len] => match len.parse::<u32>() {
+            Ok(len) => (*address, *type_name, len),
+            Err(_) => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    let (prefix, rest) = match Prefix::split(address) {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+    if prefix.numbered() {
+        let addr = match rest.parse::<u32>() {
+            Ok(addr) => addr,
+            Err(_) => return Ok(None),
+        };
+        return Ok(match (prefix, type_name) {
+            (Prefix::Counter, "COUNTER") => {
+                Some(Area::Counter(DataSizeType::Counter { addr, len }))
+            }
+            (Prefix::Timer, "TIMER") => Some(Area::Timer(DataSizeType::Timer { addr, len })),
+            _ => None,
+        });
+    }
+
+    let bit = match rest.strip_prefix(prefix.bit_prefix()) {
+        Some(bit) => bit,
+        None => return Ok(None),
+    };
+    let (addr, bit_addr) = match parse_bit(bit)? {
+        Some(bit) => bit,
+        None => return Ok(None),
+    };
+    let data = match (type_name, bit_addr as u8) {
+        ("BOOL", _) if len == 1 => DataSizeType::Bit { addr, bit_addr },
+        ("BYTE", 0) => DataSizeType::Byte { addr, len },
+        ("CHAR", 0) => DataSizeType::Char { addr, len },
+        ("WORD", 0) => DataSizeType::Word { addr, len },
+        ("INT", 0) => DataSizeType::Int { addr, len },
+        ("DWORD", 0) => DataSizeType::DWord { addr, len },
+        ("DINT", 0) => DataSizeType::DInt { addr, len },
+        ("REAL", 0) => DataSizeType::Real { addr, len },
+        _ => return Ok(None),
+    };
+    Ok(Some(prefix.area(data)))
+}
+
+#[test]
+fn test_parse_single() {
+    let cases = [
+        ("DB10.DBX4.3", "DB10.DBX4.3"),
+        ("DB1.DBW20", "DB1.DBW20"),
+        ("db1.dbd4", "DB1.DBD4"),
+        ("MW100", "MW100"),
+        ("M10.2", "M10.2"),
+        ("I0.1", "I0.1"),
+        ("E0.1", "I0.1"),
+        ("QB2", "QB2"),
+        ("AB2", "QB2"),
+        ("PIW256", "PIW256"),
+        ("PEW256", "PIW256"),
+        ("PQB4", "PQB4"),
+        ("PAD8", "PQD8"),
+        ("T5", "T5"),
+        ("C3", "C3"),
+        ("Z3", "C3"),
+        ("VD300", "VD300"),
+        ("DI2.DIB8", "DI2.DIB8"),
+        ("LW2", "LW2"),
+        ("VLB0", "VLB0"),
+    ];
+    for (input, display) in cases.iter() {
+        let area: Area = input.parse().unwrap();
+        assert_eq!(area.to_string(), *display, "{}", input);
+    }
+
+    let area: Area = "DB10.DBX4.3".parse().unwrap();
+    assert_eq!(area.area_data(), 0x84);
+    assert_eq!(area.db_number(), 10);
+    assert_eq!(area.start(), 4);
+    assert_eq!(area.bit_addr(), 3);
+    let area: Area = "PIW256".parse().unwrap();
+    assert_eq!(area.area_data(), 0x80);
+    assert_eq!(area.byte_len(), 2);
+    let area: Area = "T5".parse().unwrap();
+    assert_eq!(area.area_data(), 0x1D);
+    assert_eq!(area.data(), 0x1D);
+
+    for invalid in [
+        "",
+        "X1",
+        "MW",
+        "DB1.DBX4",
+        "DB1.DB4.1",
+        "DB1.MW2",
+        "DBX.DBW2",
+        "M1",
+        "T",
+        "QW1.2",
+        "PW4",
+        "P256.0",
+    ] {
+        match invalid.parse::<Area>() {
+            Err(Error::InvalidInput { .. }) => {}
+            other => panic!("{}: expected invalid input got {:?}", invalid, other),
+        }
+    }
+    match "DB1.DBX4.8".parse::<Area>() {
+        Err(Error::InvalidBitAddr(8)) => {}
+        other => panic!("expected invalid bit addr got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_pointer() {
+    let cases = [
+        ("P#DB1.DBX20.0 INT 5", "P#DB1.DBX20.0 INT 5"),
+        ("p#m10.0 byte 4", "P#M10.0 BYTE 4"),
+        ("P#DB1.DBX0.0 BYTE 1", "DB1.DBB0"),
+        ("P#I1.3 BOOL 1", "I1.3"),
+        ("P#V300.0 REAL 2", "P#V300.0 REAL 2"),
+        ("P#PI256.0 INT 1", "P#PI256.0 INT 1"),
+        ("P#PA4.0 WORD 2", "P#PQ4.0 WORD 2"),
+        ("P#T4 TIMER 3", "P#T4 TIMER 3"),
+        ("P#Z4 COUNTER 1", "C4"),
+    ];
+    for (input, display) in cases.iter() {
+        let area: Area = input.parse().unwrap();
+        assert_eq!(area.to_string(), *display, "{}", input);
+    }
+    let area: Area = "P#DB1.DBX20.0 INT 5".parse().unwrap();
+    assert_eq!(area.byte_len(), 10);
+
+    for invalid in [
+        "P#DB1.DBX20.0 INT",
+        "P#DB1.DBX20.1 INT 5",
+        "P#DB1.DBX20.0 LREAL 5",
+        "P#M10.0 BOOL 2",
+        "P#T4 COUNTER 1",
+        "P#MW10 INT 1",
+        "P#P256.0 INT 1",
+    ] {
+        match invalid.parse::<Area>() {
+            Err(Error::InvalidInput { .. }) => {}
+            other => panic!("{}: expected invalid input got {:?}", invalid, other),
+        }
+    }
+}
+
+#[test]
+fn test_display_round_trip() {
+    use DataSizeType::*;
+    let data = [
+        Bit {
+            addr: 4,
+            bit_addr: BitAddr::try_from(3).unwrap(),
+        },
+        Byte { addr: 2, len: 1 },
+        Byte { addr: 2, len: 6 },
+        Char { addr: 8, len: 1 },
+        Char { addr: 8, len: 10 },
+        Word { addr: 10, len: 1 },
+        Word { addr: 10, len: 3 },
+        Int { addr: 12, len: 1 },
+        Int { addr: 12, len: 4 },
+        DWord { addr: 20, len: 1 },
+        DWord { addr: 20, len: 2 },
+        DInt { addr: 24, len: 1 },
+        DInt { addr: 24, len: 2 },
+        Real { addr: 32, len: 1 },
+        Real { addr: 32, len: 5 },
+    ];
+    let mut areas = vec![
+        Area::Counter(Counter { addr: 3, len: 1 }),
+        Area::Counter(Counter { addr: 3, len: 4 }),
+        Area::Timer(Timer { addr: 5, len: 1 }),
+        Area::Timer(Timer { addr: 5, len: 2 }),
+    ];
+    for data in data.iter() {
+        areas.push(Area::ProcessInput(*data));
+        areas.push(Area::ProcessOutput(*data));
+        areas.push(Area::Merker(*data));
+        areas.push(Area::V(*data));
+        areas.push(Area::DataBausteine(7, *data));
+        areas.push(Area::PeripheralInput(*data));
+        areas.push(Area::PeripheralOutput(*data));
+        areas.push(Area::InstanceData(9, *data));
+        areas.push(Area::LocalData(*data));
+        areas.push(Area::PreviousLocalData(*data));
+    }
+    for area in areas.iter() {
+        let parsed: Area = area.to_string().parse().unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", area), "{}", area);
+    }
+}
@@ -2,495 +2,807 @@
 // This software may be modified and distributed under the terms
 // of the BSD license. See the LICENSE file for details.
 
-use super::constant::{self, Area};
+use super::constant::{self, Area, DataSizeType};
 use super::error::{self, Error};
 use super::transport::{self, Transport};
 use crate::constant::CpuStatus;
+use crate::diagnostic::{self, DiagnosticEntry};
+use crate::field::Fields;
+use crate::szl::{
+    self, CommunicationCapabilities, ComponentIdentification, Led, ModeTransition,
+    ModuleIdentification, ModuleStatus, Protection, Szl, SzlHeader,
+};
+use crate::tag::TagTable;
 use crate::tcp::{Options, TcpTransport};
+use crate::value::{self, DateTime, S7Value};
 use crate::CollectParam;
 use byteorder::{BigEndian, ByteOrder};
-use std::str;
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::fmt;
+use std::time::SystemTime;
 
-#[derive(Debug, Clone)]
+/// identification of the CPU, SZL 0x001C and 0x0011
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct CpuInfo {
-    module_type_name: String,
-    serial_number: String,
-    as_name: String,
-    copyright: String,
-    module_name: String,
+    pub module_type_name: String,
+    pub module_name: String,
+    /// name of the automation system
+    pub as_name: String,
+    pub plant_id: String,
+    pub location: String,
+    pub serial_number: String,
+    pub order_number: String,
+    pub hardware_version: Option<String>,
+    pub firmware_version: Option<String>,
+    pub copyright: String,
 }
 
-#[derive(Debug, Clone)]
+impl CpuInfo {
+    pub fn new(component: &ComponentIdentification, modules: &[ModuleIdentification]) -> CpuInfo {
+        let version = |index: u16| {
+            modules
+                .iter()
+                .find(|module| module.index == index)
+                .map(|module| module.version())
+        };
+        let order_number = modules
+            .iter()
+            .find(|module| module.index == ModuleIdentification::INDEX_MODULE)
+            .or_else(|| modules.first())
+            .map_or(String::new(), |module| module.order_number.clone());
+        CpuInfo {
+            module_type_name: component.module_type_name.clone(),
+            module_name: component.module_name.clone(),
+            as_name: component.as_name.clone(),
+            plant_id: component.plant_id.clone(),
+            location: component.location.clone(),
+            serial_number: component.serial_number.clone(),
+            order_number,
+            hardware_version: version(ModuleIdentification::INDEX_HARDWARE)
+                .or_else(|| version(ModuleIdentification::INDEX_MODULE)),
+            firmware_version: version(ModuleIdentification::INDEX_FIRMWARE),
+            copyright: component.copyright.clone(),
+        }
+    }
+}
+
+impl fmt::Display for CpuInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let none = String::new();
+        writeln!(f, "Module type name: {}", self.module_type_name)?;
+        writeln!(f, "Module name: {}", self.module_name)?;
+        writeln!(f, "AS name: {}", self.as_name)?;
+        writeln!(f, "Plant ID: {}", self.plant_id)?;
+        writeln!(f, "Location: {}", self.location)?;
+        writeln!(f, "Serial number: {}", self.serial_number)?;
+        writeln!(f, "Order number: {}", self.order_number)?;
+        writeln!(
+            f,
+            "Hardware version: {}",
+            self.hardware_version.as_ref().unwrap_or(&none)
+        )?;
+        writeln!(
+            f,
+            "Firmware version: {}",
+            self.firmware_version.as_ref().unwrap_or(&none)
+        )?;
+        write!(f, "Copyright: {}", self.copyright)
+    }
+}
+
+/// communication capabilities of the CPU, SZL 0x0131 index 1
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct CPInfo {
-    max_pdu_length: u16,
-    max_connections: u16,
-    max_mpi_rate: u16,
-    max_bus_rate: u16,
+    pub max_pdu_length: u16,
+    pub max_connections: u16,
+    /// bit/s
+    pub max_mpi_rate: u32,
+    /// bit/s of the communication bus
+    pub max_bus_rate: u32,
+}
+
+impl fmt::Display for CPInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Max PDU length: {}", self.max_pdu_length)?;
+        writeln!(f, "Max connections: {}", self.max_connections)?;
+        writeln!(f, "Max MPI rate: {} bit/s", self.max_mpi_rate)?;
+        write!(f, "Max bus rate: {} bit/s", self.max_bus_rate)
+    }
+}
+
+/// identity of a CPU for asset inventories
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Identity {
+    pub cpu: CpuInfo,
+    pub cp: CPInfo,
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.cpu)?;
+        write!(f, "{}", self.cp)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Client<T: Transport> {
     transport: T,
+    tags: TagTable,
 }
 impl Client<TcpTransport> {
     pub fn init_by_options(param: &CollectParam) -> Result<Client<TcpTransport>, Error> {
-        let opts = Options::init_from_config(&param);
+        let opts = Options::init_from_config(param);
         let t = TcpTransport::connect(opts)?;
-        Ok(Client::new(t)?)
+        Client::new(t)
     }
 }
 impl<T: Transport> Client<T> {
     pub fn new(mut transport: T) -> Result<Client<T>, Error> {
         transport.negotiate()?;
-        Ok(Client { transport })
+        Ok(Client {
+            transport,
+            tags: TagTable::default(),
+        })
     }
 
-    /// # Examples
-    ///
-    /// ```no_run
-    /// use std::net::{Ipv4Addr, IpAddr};
-    /// use s7::{client, tcp, transport};
-    /// use std::time::Duration;
-    /// use s7::field::{Bool, Field};
-    ///
-    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
-    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
-    ///
-    /// opts.read_timeout = Duration::from_secs(2);
-    /// opts.write_timeout = Duration::from_secs(2);
-    ///
-    /// let t = tcp::TcpTransport::connect(opts).unwrap();
-    /// let mut cl = client::Client::new(t).unwrap();
-    ///
-    /// let buffer = &mut vec![0u8; Bool::size() as usize];
-    /// let db = 888;
-    /// let offset = 8.4;
-    ///
-    /// cl.db_read(db, offset as i32, Bool::size(), buffer).unwrap();
-    ///
-    /// let mut  lights = Bool::new(db, offset, buffer.to_vec()).unwrap();
-    /// lights.set_value(!lights.value()); // toggle the light switch
-    ///
-    /// // save
-    /// cl.db_write(
-    ///     lights.data_block(),
-    ///     lights.offset(),
-    ///     Bool::size(),
-    ///     lights.to_bytes().as_mut()
-    /// ).unwrap();
-    ///
-    /// ```
-    // pub fn db_read(&mut self, db_number: u16, data_type: DataSizeType) -> Result<Vec<u8>, Error> {
-    //     return self.read(Area::DataBausteine, db_number, data_type);
-    // }
-
-    /// # Examples
-    ///
-    /// ```no_run
-    /// use std::net::{Ipv4Addr, IpAddr};
-    /// use s7::{client, tcp, transport};
-    /// use std::time::Duration;
-    /// use s7::field::{Bool, Field};
-    ///
-    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
-    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
-    ///
-    /// opts.read_timeout = Duration::from_secs(2);
-    /// opts.write_timeout = Duration::from_secs(2);
-    ///
-    /// let t = tcp::TcpTransport::connect(opts).unwrap();
-    /// let mut cl = client::Client::new(t).unwrap();
-    ///
-    /// let buffer = &mut vec![0u8; Bool::size() as usize];
-    /// let db = 888;
-    /// let offset = 8.4;
-    ///
-    /// cl.db_read(db, offset as i32, Bool::size(), buffer).unwrap();
-    ///
-    /// let mut  lights = Bool::new(db, offset, buffer.to_vec()).unwrap();
-    /// lights.set_value(!lights.value()); // toggle the light switch
-    ///
-    /// // save
-    /// cl.db_write(
-    ///     lights.data_block(),
-    ///     lights.offset(),
-    ///     Bool::size(),
-    ///     lights.to_bytes().as_mut()
-    /// ).unwrap();
-    ///
-    /// ```
-    // pub fn db_write(
-    //     &mut self,
-    //     db_number: i32,
-    //     start: i32,
-    //     size: i32,
-    //     buffer: &mut Vec<u8>,
-    // ) -> Result<(), Error> {
-    //     return self.write(
-    //         Area::DataBausteine,
-    //         db_number,
-    //         start,
-    //         size,
-    //         constant::WL_BYTE,
-    //         buffer,
-    //     );
-    // }
-
-    /// # Examples
-    ///
-    /// ```no_run
-    /// use std::net::{Ipv4Addr, IpAddr};
-    /// use s7::{client, tcp, transport};
-    /// use std::time::Duration;
-    ///
-    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
-    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
-    ///
-    /// opts.read_timeout = Duration::from_secs(2);
-    /// opts.write_timeout = Duration::from_secs(2);
-    ///
-    ///
-    /// let t = tcp::TcpTransport::connect(opts).unwrap();
-    /// let mut cl = client::Client::new(t).unwrap();
-    ///
-    /// let buffer = &mut vec![0u8; 255];
-    ///
-    /// cl.mb_read(1, 3, buffer).unwrap();
-    /// ```
-    // pub fn mb_read(&mut self, start: i32, size: i32, buffer: &mut Vec<u8>) -> Result<(), Error> {
-    //     return self.read(Area::Merker, 0, start, size, constant::WL_BYTE, buffer);
-    // }
-
-    /// # Examples
-    ///
-    /// ```no_run
-    /// use std::net::{Ipv4Addr, IpAddr};
-    /// use s7::{client, tcp, transport};
-    /// use std::time::Duration;
-    ///
-    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
-    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
-    ///
-    /// opts.read_timeout = Duration::from_secs(2);
-    /// opts.write_timeout = Duration::from_secs(2);
-    ///
-    ///
-    /// let t = tcp::TcpTransport::connect(opts).unwrap();
-    /// let mut cl = client::Client::new(t).unwrap();
-    ///
-    /// let buffer = &mut vec![0u8; 255];
-    ///
-    /// cl.mb_write(1, 3, buffer).unwrap();
-    /// ```
-    // pub fn mb_write(&mut self, start: i32, size: i32, buffer: &mut Vec<u8>) -> Result<(), Error> {
-    //     return self.write(Area::Merker, 0, start, size, constant::WL_BYTE, buffer);
-    // }
-
-    // pub fn di_read(&mut self, data_type: DataSizeType) -> Result<Vec<u8>, Error> {
-    //     return self.read(Area::ProcessInput, 0, data_type);
-    // }
-
-    /// # Examples
-    ///
-    /// ```no_run
-    /// use std::net::{Ipv4Addr, IpAddr};
-    /// use s7::{client, tcp, transport};
-    /// use std::time::Duration;
-    ///
-    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
-    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
-    ///
-    /// opts.read_timeout = Duration::from_secs(2);
-    /// opts.write_timeout = Duration::from_secs(2);
-    ///
-    ///
-    /// let t = tcp::TcpTransport::connect(opts).unwrap();
-    /// let mut cl = client::Client::new(t).unwrap();
+    /// read generic area, the result contains `area.byte_len()` bytes
     ///
-    /// let buffer = &mut vec![0u8; 255];
-    ///
-    /// cl.di_write(1, 3, buffer).unwrap();
-    /// ```
-    // pub fn di_write(&mut self, start: i32, size: i32, buffer: &mut Vec<u8>) -> Result<(), Error> {
-    //     return self.write(
-    //         Area::ProcessInput,
-    //         0,
-    //         start,
-    //         size,
-    //         constant::WL_BYTE,
-    //         buffer,
-    //     );
-    // }
-
-    /// read ProcessOutput
-    /// start: addr
-    /// size: length
-    // pub fn dq_read(&mut self, data_type: DataSizeType) -> Result<Vec<u8>, Error> {
-    //     return self.read(Area::ProcessOutput, 0, data_type);
-    // }
-
-    /// # Examples
-    ///
-    /// ```no_run
-    /// use std::net::{Ipv4Addr, IpAddr};
-    /// use s7::{client, tcp, transport};
-    /// use std::time::Duration;
-    ///
-    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
-    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
-    ///
-    /// opts.read_timeout = Duration::from_secs(2);
-    /// opts.write_timeout = Duration::from_secs(2);
-    ///
-    ///
-    /// let t = tcp::TcpTransport::connect(opts).unwrap();
-    /// let mut cl = client::Client::new(t).unwrap();
-    ///
-    /// let buffer = &mut vec![0u8; 255];
-    ///
-    /// cl.do_write(1, 3, buffer).unwrap();
-    /// ```
-    // pub fn dq_write(&mut self, start: i32, size: i32, buffer: &mut Vec<u8>) -> Result<(), Error> {
-    //     return self.write(
-    //         Area::ProcessOutput,
-    //         0,
-    //         start,
-    //         size,
-    //         constant::WL_BYTE,
-    //         buffer,
-    //     );
-    // }
-
-    //read generic area, pass result into a buffer
-    ///
-    /// Transport Size固定为Byte
-    /// 3位的Bit addr固定为0
+    /// Areas larger than the negotiated pdu are split into several requests,
+    /// the start address of every request advances by the bytes already read.
     pub fn read(&mut self, area: Area) -> Result<Vec<u8>, Error> {
-        let pdu_length = self.transport.pdu_length();
+        area.check_read()?;
+        let pdu_length = self.transport.pdu_length() as usize;
 
-        if pdu_length == 0 {
-            return Err(Error::PduLength(pdu_length));
+        // 18 = Reply telegram header
+        if pdu_length <= 18 + area.length() as usize {
+            return Err(Error::PduLength(pdu_length as u16));
         }
 
-        let amount = area.len();
-        // let max_elements = (pdu_length - 18) / amount; // 18 = Reply telegram header //lth note here
-        let max_elements = amount / (pdu_length - 18) + amount % (pdu_length - 18);
-
-        let mut tot_elements = amount;
+        let word_size = area.length() as usize;
+        let max_elements = (pdu_length - 18) / word_size;
         let db_bytes = area.db_number().to_be_bytes();
-        let mut offset = 0;
 
+        let mut tot_elements = area.len() as usize;
         let mut buffer = Vec::with_capacity(area.byte_len());
         while tot_elements > 0 {
-            let mut num_elements = tot_elements;
-
-            if num_elements > max_elements {
-                num_elements = max_elements;
-            }
+            let num_elements = tot_elements.min(max_elements);
+            let size_requested = num_elements * word_size;
 
-            let size_requested = num_elements * area.length();
             // Setup the telegram
             let mut request =
                 transport::READ_WRITE_TELEGRAM[..constant::SIZE_HEADER_READ as usize].to_vec();
-
-            // Adjusts Start and word length
-            // let mut address = match word_len {
-            //     constant::WL_BIT | constant::WL_COUNTER | constant::WL_TIMER => {
-            //         request[22] = word_len as u8;
-            //         start
-            //     }
-            //     _ => start << 3,
-            // };
             request[22] = area.data();
             // Num elements
-            let num_elements_bytes = num_elements.to_be_bytes();
-            request[23] = num_elements_bytes[0];
-            request[24] = num_elements_bytes[1];
+            BigEndian::write_u16(request[23..].as_mut(), num_elements as u16);
             // Set DB Number
             request[25] = db_bytes[0];
             request[26] = db_bytes[1];
-
             // Set Area
             request[27] = area.area_data();
-            // match area {
-            //     Area::DataBausteine => request[27] = area as u8,
-            //     _ => {}
-            // }
+            // Address into the PLC (only 3 bytes)
+            request[28..31].copy_from_slice(&area.addr_at(buffer.len())?);
+
+            let response = self.transport.send(request.as_slice())?;
+
+            if response.len() < 25 {
+                return Err(Error::Response {
+                    code: error::ISO_INVALID_DATA_SIZE,
+                });
+            }
 
+            let header_error = BigEndian::read_u16(response[17..].as_ref());
+            if header_error != 0 {
+                return Err(Error::CPU {
+                    code: header_error as i32,
+                });
+            }
+
+            if response[21] != 0xFF {
+                return Err(Error::CPU {
+                    code: response[21] as i32,
+                });
+            }
+
+            let size = response_item_len(response[22], BigEndian::read_u16(&response[23..]));
+            if size != size_requested || response.len() < 25 + size {
+                return Err(Error::Response {
+                    code: error::CLI_INVALID_DATA_SIZE_RECVD,
+                });
+            }
+
+            buffer.extend_from_slice(&response[25..25 + size]);
+            tot_elements -= num_elements;
+        }
+
+        if buffer.len() != area.byte_len() {
+            return Err(Error::Response {
+                code: error::CLI_PARTIAL_DATA_READ,
+            });
+        }
+        Ok(buffer)
+    }
+
+    /// write generic area, `data` has to contain exactly `area.byte_len()` bytes
+    ///
+    /// Bit areas are written as a single bit, Counter and Timer areas element by element,
+    /// every other type is sent as bytes.
+    pub fn write(&mut self, area: Area, data: &[u8]) -> Result<(), Error> {
+        area.check_write()?;
+        if data.len() != area.byte_len() {
+            return Err(Error::InvalidInput {
+                input: format!(
+                    "write {:?}: expected {} bytes got {}",
+                    area,
+                    area.byte_len(),
+                    data.len()
+                ),
+            });
+        }
+
+        let pdu_length = self.transport.pdu_length();
+        if pdu_length <= constant::SIZE_HEADER_WRITE as u16 {
+            return Err(Error::PduLength(pdu_length));
+        }
+
+        let (transport_size, word_size) = write_transport_size(&area);
+        let return_transport_size = area.return_transport_size();
+        let max_elements = (pdu_length as usize - constant::SIZE_HEADER_WRITE as usize) / word_size;
+        let db_bytes = area.db_number().to_be_bytes();
+
+        let mut offset = 0;
+        while offset < data.len() {
+            let num_elements = ((data.len() - offset) / word_size).min(max_elements);
+            let data_size = num_elements * word_size;
+
+            // Setup the telegram
+            let mut request = transport::READ_WRITE_TELEGRAM.to_vec();
+            // Whole telegram Size
+            BigEndian::write_u16(
+                request[2..].as_mut(),
+                (constant::SIZE_HEADER_WRITE as usize + data_size) as u16,
+            );
+            // Data length
+            BigEndian::write_u16(request[15..].as_mut(), (data_size + 4) as u16);
+            // Function
+            request[17] = 0x05;
+            request[22] = transport_size;
+            // Num elements
+            BigEndian::write_u16(request[23..].as_mut(), num_elements as u16);
+            // Set DB Number
+            request[25] = db_bytes[0];
+            request[26] = db_bytes[1];
+            // Set Area
+            request[27] = area.area_data();
             // Address into the PLC (only 3 bytes)
-            let address = area.addr();
-            request[28] = address[0];
-            request[29] = address[1];
-            request[30] = address[2];
-
-            let result = self.transport.send(request.as_slice());
-
-            match result {
-                Ok(response) => {
-                    if response.len() < 25 {
-                        return Err(Error::Response {
-                            code: error::ISO_INVALID_DATA_SIZE,
-                        });
-                    }
+            request[28..31].copy_from_slice(&area.addr_at(offset)?);
+            // Transport size of the data
+            request[32] = return_transport_size;
+            // Length
+            BigEndian::write_u16(
+                request[33..].as_mut(),
+                data_item_length(return_transport_size, data_size),
+            );
+            request.extend_from_slice(&data[offset..offset + data_size]);
+
+            let response = self.transport.send(request.as_slice())?;
+
+            if response.len() < 22 {
+                return Err(Error::Response {
+                    code: error::ISO_INVALID_PDU,
+                });
+            }
 
-                    if response[21] != 0xFF {
-                        return Err(Error::CPU {
-                            code: response[21] as i32,
-                        });
-                    }
-                    let (mut i, end): (usize, usize) = (25, 25 + (size_requested as usize));
-
-                    //copy response to buffer
-                    for _ in offset..size_requested {
-                        if i == end {
-                            break;
-                        }
-                        buffer.push(response[i]);
-                        i += 1;
-                    }
-                    offset += size_requested;
+            let header_error = BigEndian::read_u16(response[17..].as_ref());
+            if header_error != 0 {
+                return Err(Error::CPU {
+                    code: header_error as i32,
+                });
+            }
+
+            if response[21] != 0xFF {
+                return Err(Error::CPU {
+                    code: response[21] as i32,
+                });
+            }
+
+            offset += data_size;
+        }
+        Ok(())
+    }
+
+    /// read generic area and decode the data according to its `DataSizeType`
+    pub fn read_values(&mut self, area: Area) -> Result<Vec<S7Value>, Error> {
+        let bytes = self.read(area)?;
+        S7Value::decode(&area, &bytes)
+    }
+
+    /// read a single value, the area must have a length of one
+    pub fn read_value(&mut self, area: Area) -> Result<S7Value, Error> {
+        if area.len() != 1 {
+            return Err(Error::InvalidInput {
+                input: format!("read_value {:?}: expected a single value", area),
+            });
+        }
+        Ok(self.read_values(area)?.remove(0))
+    }
+
+    /// encode `values` according to the `DataSizeType` of the area and write them
+    pub fn write_values(&mut self, area: Area, values: &[S7Value]) -> Result<(), Error> {
+        let bytes = S7Value::encode(&area, values)?;
+        self.write(area, &bytes)
+    }
+
+    /// symbols used by `read_tag` and `write_tag`
+    pub fn set_tags(&mut self, tags: TagTable) {
+        self.tags = tags;
+    }
+
+    pub fn tags(&self) -> &TagTable {
+        &self.tags
+    }
+
+    /// read the value of the tag `name` of the symbol table set with `set_tags`
+    pub fn read_tag(&mut self, name: &str) -> Result<S7Value, Error> {
+        let area = self.tags.area(name)?;
+        self.read_value(area)
+    }
+
+    /// write the value of the tag `name` of the symbol table set with `set_tags`
+    pub fn write_tag(&mut self, name: &str, value: S7Value) -> Result<(), Error> {
+        let area = self.tags.area(name)?;
+        self.write_values(area, &[value])
+    }
+
+    /// read several areas with as few ReadVar jobs as possible
+    ///
+    /// Items are packed into one job until the negotiated pdu length or `constant::MAX_VARS`
+    /// is reached. An area which does not fit into a single pdu is read on its own with `read`.
+    /// The outer error is returned when a whole job fails, item errors are returned per item
+    /// in the same order as `areas`.
+    pub fn read_multi(&mut self, areas: &[Area]) -> Result<Vec<Result<Vec<u8>, Error>>, Error> {
+        let pdu_length = self.transport.pdu_length() as usize;
+        if pdu_length <= READ_MULTI_RESPONSE_HEADER + ITEM_RESPONSE_HEADER {
+            return Err(Error::PduLength(pdu_length as u16));
+        }
+
+        let mut results: Vec<Option<Result<Vec<u8>, Error>>> = areas.iter().map(|_| None).collect();
+        let mut job: Vec<usize> = Vec::new();
+        let (mut request_size, mut response_size) =
+            (READ_MULTI_REQUEST_HEADER, READ_MULTI_RESPONSE_HEADER);
+
+        for (i, area) in areas.iter().enumerate() {
+            if let Err(e) = area.check_read() {
+                results[i] = Some(Err(e));
+                continue;
+            }
+            let item_size = ITEM_RESPONSE_HEADER + area.byte_len() + area.byte_len() % 2;
+            if READ_MULTI_RESPONSE_HEADER + item_size > pdu_length {
+                results[i] = Some(self.read(*area));
+                continue;
+            }
+            if job.len() == constant::MAX_VARS
+                || request_size + ITEM_SPEC_SIZE > pdu_length
+                || response_size + item_size > pdu_length
+            {
+                self.read_multi_job(areas, &job, &mut results)?;
+                job.clear();
+                request_size = READ_MULTI_REQUEST_HEADER;
+                response_size = READ_MULTI_RESPONSE_HEADER;
+            }
+            job.push(i);
+            request_size += ITEM_SPEC_SIZE;
+            response_size += item_size;
+        }
+        if !job.is_empty() {
+            self.read_multi_job(areas, &job, &mut results)?;
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    fn read_multi_job(
+        &mut self,
+        areas: &[Area],
+        job: &[usize],
+        results: &mut [Option<Result<Vec<u8>, Error>>],
+    ) -> Result<(), Error> {
+        // Setup the telegram
+        let mut request = transport::READ_WRITE_TELEGRAM[..19].to_vec();
+        for i in job {
+            request.extend_from_slice(&item_spec(
+                &areas[*i],
+                areas[*i].data(),
+                areas[*i].len() as u16,
+            )?);
+        }
+        // Whole telegram Size
+        let request_size = request.len() as u16;
+        BigEndian::write_u16(request[2..].as_mut(), request_size);
+        // Parameters length
+        BigEndian::write_u16(
+            request[13..].as_mut(),
+            (2 + job.len() * ITEM_SPEC_SIZE) as u16,
+        );
+        // Data length
+        BigEndian::write_u16(request[15..].as_mut(), 0);
+        // Items count
+        request[18] = job.len() as u8;
+
+        let response = self.transport.send(request.as_slice())?;
+
+        if response.len() < 21 {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+            });
+        }
+
+        let header_error = BigEndian::read_u16(response[17..].as_ref());
+        if header_error != 0 {
+            return Err(Error::CPU {
+                code: header_error as i32,
+            });
+        }
+
+        if response[20] as usize != job.len() {
+            return Err(Error::Response {
+                code: error::CLI_INVALID_PLC_ANSWER,
+            });
+        }
+
+        let mut offset = 21;
+        for (n, i) in job.iter().enumerate() {
+            if response.len() < offset + ITEM_RESPONSE_HEADER {
+                return Err(Error::Response {
+                    code: error::ISO_INVALID_DATA_SIZE,
+                });
+            }
+
+            if response[offset] != 0xFF {
+                results[*i] = Some(Err(Error::CPU {
+                    code: response[offset] as i32,
+                }));
+                offset += ITEM_RESPONSE_HEADER;
+                continue;
+            }
+
+            let size = response_item_len(
+                response[offset + 1],
+                BigEndian::read_u16(response[offset + 2..].as_ref()),
+            );
+            offset += ITEM_RESPONSE_HEADER;
+            if response.len() < offset + size {
+                return Err(Error::Response {
+                    code: error::ISO_INVALID_DATA_SIZE,
+                });
+            }
+
+            results[*i] = Some(if size == areas[*i].byte_len() {
+                Ok(response[offset..offset + size].to_vec())
+            } else {
+                Err(Error::Response {
+                    code: error::CLI_INVALID_DATA_SIZE_RECVD,
+                })
+            });
+
+            // every item but the last one is padded to an even length
+            offset += size;
+            if size % 2 == 1 && n + 1 < job.len() {
+                offset += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// write several areas with as few WriteVar jobs as possible
+    ///
+    /// Items are packed into one job until the negotiated pdu length or `constant::MAX_VARS`
+    /// is reached. An item which does not fit into a single pdu is written on its own with `write`.
+    ///
+    /// Every item is validated before anything is sent, an invalid item fails the whole call. A
+    /// failed job, ie a connection error, is returned as is. When the PLC rejects only some of the
+    /// items the data was partially written and `Error::PartialWrite` is returned with the result
+    /// of every item in the order of `items`, its code is `CLI_PARTIAL_DATA_WRITTEN`. When every
+    /// item is rejected the error of the first item is returned, the PLC return code is available
+    /// with `Error::code`.
+    pub fn write_multi(&mut self, items: &[(Area, Vec<u8>)]) -> Result<(), Error> {
+        let pdu_length = self.transport.pdu_length() as usize;
+        if pdu_length <= WRITE_MULTI_REQUEST_HEADER + ITEM_SPEC_SIZE + ITEM_RESPONSE_HEADER {
+            return Err(Error::PduLength(pdu_length as u16));
+        }
+        for (area, data) in items {
+            area.check_write()?;
+            if data.len() != area.byte_len() {
+                return Err(Error::InvalidInput {
+                    input: format!(
+                        "write {:?}: expected {} bytes got {}",
+                        area,
+                        area.byte_len(),
+                        data.len()
+                    ),
+                });
+            }
+        }
+
+        let mut results: Vec<Option<Result<(), Error>>> = items.iter().map(|_| None).collect();
+        let mut job: Vec<usize> = Vec::new();
+        let mut request_size = WRITE_MULTI_REQUEST_HEADER;
+
+        for (i, (area, data)) in items.iter().enumerate() {
+            let item_size = ITEM_SPEC_SIZE + ITEM_RESPONSE_HEADER + data.len() + data.len() % 2;
+            if WRITE_MULTI_REQUEST_HEADER + item_size > pdu_length {
+                results[i] = Some(match self.write(*area, data) {
+                    Err(e @ Error::CPU { .. }) => Err(e),
+                    Err(e) => return Err(e),
+                    Ok(()) => Ok(()),
+                });
+                continue;
+            }
+            if job.len() == constant::MAX_VARS || request_size + item_size > pdu_length {
+                self.write_multi_job(items, &job, &mut results)?;
+                job.clear();
+                request_size = WRITE_MULTI_REQUEST_HEADER;
+            }
+            job.push(i);
+            request_size += item_size;
+        }
+        if !job.is_empty() {
+            self.write_multi_job(items, &job, &mut results)?;
+        }
+
+        let results: Vec<Result<(), Error>> = results.into_iter().flatten().collect();
+        let failed = results.iter().filter(|result| result.is_err()).count();
+        if failed > 0 && failed < results.len() {
+            return Err(Error::PartialWrite { results });
+        }
+        results.into_iter().collect()
+    }
+
+    fn write_multi_job(
+        &mut self,
+        items: &[(Area, Vec<u8>)],
+        job: &[usize],
+        results: &mut [Option<Result<(), Error>>],
+    ) -> Result<(), Error> {
+        let codes = self.send_write_multi_job(items, job)?;
+        for (i, code) in job.iter().zip(codes) {
+            results[*i] = Some(if code == 0xFF {
+                Ok(())
+            } else {
+                Err(Error::CPU { code: code as i32 })
+            });
+        }
+        Ok(())
+    }
+
+    /// sends a single WriteVar job and returns the return code of every item
+    fn send_write_multi_job(
+        &mut self,
+        items: &[(Area, Vec<u8>)],
+        job: &[usize],
+    ) -> Result<Vec<u8>, Error> {
+        // Setup the telegram
+        let mut request = transport::READ_WRITE_TELEGRAM[..19].to_vec();
+        for i in job {
+            let area = &items[*i].0;
+            let (transport_size, word_size) = write_transport_size(area);
+            request.extend_from_slice(&item_spec(
+                area,
+                transport_size,
+                (area.byte_len() / word_size) as u16,
+            )?);
+        }
+        let param_length = request.len() - 17;
+        for (n, i) in job.iter().enumerate() {
+            let (area, data) = &items[*i];
+            let return_transport_size = area.return_transport_size();
+            request.extend_from_slice(&[0, return_transport_size]);
+            request.extend_from_slice(
+                &data_item_length(return_transport_size, data.len()).to_be_bytes(),
+            );
+            request.extend_from_slice(data);
+            // every item but the last one is padded to an even length
+            if data.len() % 2 == 1 && n + 1 < job.len() {
+                request.push(0);
+            }
+        }
+        let data_length = request.len() - 17 - param_length;
+        // Whole telegram Size
+        let request_size = request.len() as u16;
+        BigEndian::write_u16(request[2..].as_mut(), request_size);
+        // Parameters length
+        BigEndian::write_u16(request[13..].as_mut(), param_length as u16);
+        // Data length
+        BigEndian::write_u16(request[15..].as_mut(), data_length as u16);
+        // Function
+        request[17] = 0x05;
+        // Items count
+        request[18] = job.len() as u8;
+
+        let response = self.transport.send(request.as_slice())?;
+
+        if response.len() < 21 {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+            });
+        }
+
+        let header_error = BigEndian::read_u16(response[17..].as_ref());
+        if header_error != 0 {
+            return Err(Error::CPU {
+                code: header_error as i32,
+            });
+        }
+
+        if response[20] as usize != job.len() || response.len() < 21 + job.len() {
+            return Err(Error::Response {
+                code: error::CLI_INVALID_PLC_ANSWER,
+            });
+        }
+
+        Ok(response[21..21 + job.len()].to_vec())
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// refreshes all fields with as few ReadVar jobs as possible
+    ///
+    /// Fields in the same area and data block which are close to each other are read as a
+    /// single item, a `Bool` is read with the byte containing it. The result of every field is
+    /// returned in the same order as `fields`, afterwards the loaded fields are no longer dirty.
+    pub fn load_fields(&mut self, fields: &mut Fields) -> Result<Vec<Result<(), Error>>, Error> {
+        let mut order: Vec<usize> = (0..fields.len()).collect();
+        order.sort_by_key(|i| {
+            let area = fields[*i].area();
+            (area.area_data(), area.db_number(), area.start())
+        });
+
+        // merged byte ranges and the fields they contain
+        let mut ranges: Vec<(Area, Vec<usize>)> = Vec::new();
+        for i in order {
+            let area = fields[i].area();
+            let end = area.start() + area.byte_len() as u32;
+            if let Some((range, members)) = ranges.last_mut() {
+                if range.area_data() == area.area_data()
+                    && range.db_number() == area.db_number()
+                    && area.start() <= range.start() + range.len() + FIELD_MERGE_GAP
+                {
+                    let len = end.max(range.start() + range.len()) - range.start();
+                    *range = range.with_data(DataSizeType::Byte {
+                        addr: range.start(),
+                        len,
+                    });
+                    members.push(i);
+                    continue;
                 }
-                Err(e) => {
-                    return Err(e);
+            }
+            let range = area.with_data(DataSizeType::Byte {
+                addr: area.start(),
+                len: end - area.start(),
+            });
+            ranges.push((range, vec![i]));
+        }
+
+        let areas: Vec<Area> = ranges.iter().map(|(range, _)| *range).collect();
+        let data = self.read_multi(&areas)?;
+
+        let mut results: Vec<Option<Result<(), Error>>> = fields.iter().map(|_| None).collect();
+        for ((range, members), data) in ranges.iter().zip(data) {
+            for i in members {
+                results[*i] = Some(match &data {
+                    Ok(data) => {
+                        let area = fields[*i].area();
+                        let offset = (area.start() - range.start()) as usize;
+                        fields[*i].load(&data[offset..offset + area.byte_len()])
+                    }
+                    Err(e) => Err(e.clone()),
+                });
+            }
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// writes back the fields which changed since they were created or loaded
+    ///
+    /// A `Bool` is written as a single bit, so the other bits of its byte are never
+    /// overwritten with stale values. The result of every field is returned in the same order as
+    /// `fields`, unchanged fields are not sent and return `Ok`, a partial write is reported per
+    /// field. Any other error of `write_multi` is returned as is. Fields which were written are
+    /// no longer dirty, fields which failed stay dirty so they are sent again by the next call.
+    pub fn store_fields(&mut self, fields: &mut Fields) -> Result<Vec<Result<(), Error>>, Error> {
+        let mut dirty: Vec<usize> = Vec::new();
+        let mut items: Vec<(Area, Vec<u8>)> = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            if !field.is_dirty() {
+                continue;
+            }
+            let data = match field.bit() {
+                Some(bit) => vec![(field.to_bytes()[0] >> bit as u8) & 0x01],
+                None => field.to_bytes(),
+            };
+            dirty.push(i);
+            items.push((field.area(), data));
+        }
+
+        let mut results: Vec<Result<(), Error>> = fields.iter().map(|_| Ok(())).collect();
+        if items.is_empty() {
+            return Ok(results);
+        }
+        match self.write_multi(&items) {
+            Ok(()) => {}
+            Err(Error::PartialWrite { results: written }) => {
+                for (i, result) in dirty.iter().zip(written) {
+                    results[*i] = result;
                 }
             }
-            tot_elements -= num_elements;
+            Err(e) => return Err(e),
         }
-        Ok(buffer)
+        for i in dirty {
+            if results[i].is_ok() {
+                let bytes = fields[i].to_bytes();
+                fields[i].load(&bytes)?;
+            }
+        }
+        Ok(results)
     }
+}
 
-    // fn write(
-    //     &mut self,
-    //     area: Area,
-    //     db_number: i32,
-    //     mut start: i32,
-    //     mut amount: i32,
-    //     mut word_len: i32,
-    //     buffer: &mut Vec<u8>,
-    // ) -> Result<(), Error> {
-    //     // Some adjustment
-    //     word_len = match area {
-    //         Area::Counter => constant::WL_COUNTER,
-    //         Area::Timer => constant::WL_TIMER,
-    //         _ => word_len,
-    //     };
-    //
-    //     // Calc Word size
-    //     let mut word_size = constant::data_size_byte(word_len);
-    //
-    //     if word_size == 0 {
-    //         return Err(Error::Response {
-    //             code: error::ISO_INVALID_DATA_SIZE,
-    //         });
-    //     }
-    //
-    //     if word_len == constant::WL_BIT {
-    //         amount = 1; // Only 1 bit can be transferred at time
-    //     } else {
-    //         if word_len != constant::WL_COUNTER && word_len != constant::WL_TIMER {
-    //             amount = amount * word_size;
-    //             word_size = 1;
-    //             word_len = constant::WL_BYTE;
-    //         }
-    //     }
-    //
-    //     let mut offset: i32 = 0;
-    //     let pdu_length = self.transport.pdu_length();
-    //     let max_elements = (pdu_length - 35) as i32 / word_size; // 35 = Reply telegram header
-    //     let mut tot_elements = amount;
-    //
-    //     while tot_elements > 0 {
-    //         let mut num_elements = tot_elements;
-    //         if num_elements > max_elements {
-    //             num_elements = max_elements;
-    //         }
-    //         let data_size = num_elements * word_size;
-    //         let iso_size = constant::SIZE_HEADER_WRITE + data_size;
-    //
-    //         // Setup the telegram
-    //         let mut request_data = transport::READ_WRITE_TELEGRAM.to_vec();
-    //         // Whole telegram Size
-    //         BigEndian::write_u16(request_data[2..].as_mut(), iso_size as u16);
-    //         // Data length
-    //         let mut length = data_size + 4;
-    //         BigEndian::write_u16(request_data[15..].as_mut(), length as u16);
-    //         // Function
-    //         request_data[17] = 0x05;
-    //         // Set DB Number
-    //         request_data[27] = area as u8;
-    //
-    //         match area {
-    //             Area::DataBausteine => {
-    //                 BigEndian::write_u16(request_data[25..].as_mut(), db_number as u16)
-    //             }
-    //             _ => {}
-    //         }
-    //         // Adjusts start and word length
-    //         let mut address = match word_len {
-    //             constant::WL_BIT | constant::WL_COUNTER | constant::WL_TIMER => {
-    //                 length = data_size;
-    //                 request_data[22] = word_len as u8;
-    //                 start
-    //             }
-    //             _ => {
-    //                 length = data_size << 3;
-    //                 start << 3
-    //             }
-    //         };
-    //
-    //         // Num elements
-    //         BigEndian::write_u16(request_data[23..].as_mut(), num_elements as u16);
-    //         // address into the PLC
-    //         request_data[30] = (address & 0x0FF) as u8;
-    //         address = address >> 8;
-    //         request_data[29] = (address & 0x0FF) as u8;
-    //         address = address >> 8;
-    //         request_data[28] = (address & 0x0FF) as u8;
-    //
-    //         // Transport Size
-    //         match word_len {
-    //             constant::WL_BIT => request_data[32] = constant::TS_RES_BIT as u8,
-    //             constant::WL_COUNTER | constant::WL_TIMER => {
-    //                 request_data[32] = constant::TS_RES_OCTET as u8
-    //             }
-    //             _ => request_data[32] = constant::TS_RES_BYTE as u8, // byte/word/dword etc.
-    //         }
-    //         // length
-    //         BigEndian::write_u16(request_data[33..].as_mut(), length as u16);
-    //
-    //         //expand values into array
-    //         request_data.splice(
-    //             35..35,
-    //             buffer[offset as usize..offset as usize + data_size as usize].to_vec(),
-    //         );
-    //
-    //         let result = self.transport.send(request_data.as_mut_slice());
-    //
-    //         match result {
-    //             Ok(response) => {
-    //                 if response.len() != 22 {
-    //                     return Err(Error::Response {
-    //                         code: error::ISO_INVALID_PDU,
-    //                     });
-    //                 }
-    //
-    //                 if response[21] != 0xFF {
-    //                     return Err(Error::CPU {
-    //                         code: response[21] as i32,
-    //                     });
-    //                 }
-    //             }
-    //             Err(e) => {
-    //                 return Err(e);
-    //             }
-    //         }
-    //
-    //         offset += data_size;
-    //         tot_elements -= num_elements;
-    //         start += num_elements * word_size;
-    //     }
-    //     Ok(())
-    // }
+/// size of an item spec in a ReadVar/WriteVar request
+const ITEM_SPEC_SIZE: usize = 12;
+/// size of the return code, transport size and length preceding the data of a response item
+const ITEM_RESPONSE_HEADER: usize = 4;
+/// S7 header and function/items count of a ReadVar request
+const READ_MULTI_REQUEST_HEADER: usize = 12;
+/// S7 ack header and function/items count of a ReadVar response
+const READ_MULTI_RESPONSE_HEADER: usize = 14;
+
+/// S7 header and function/items count of a WriteVar request
+const WRITE_MULTI_REQUEST_HEADER: usize = 12;
+/// fields this many bytes apart are read as one item, reading the gap is cheaper than another item
+const FIELD_MERGE_GAP: u32 = (ITEM_SPEC_SIZE + ITEM_RESPONSE_HEADER) as u32;
+
+/// item spec addressing `area`
+fn item_spec(
+    area: &Area,
+    transport_size: u8,
+    num_elements: u16,
+) -> Result<[u8; ITEM_SPEC_SIZE], Error> {
+    let mut spec = [0u8; ITEM_SPEC_SIZE];
+    spec[..3].copy_from_slice(&transport::READ_WRITE_TELEGRAM[19..22]);
+    spec[3] = transport_size;
+    BigEndian::write_u16(spec[4..].as_mut(), num_elements);
+    BigEndian::write_u16(spec[6..].as_mut(), area.db_number());
+    spec[8] = area.area_data();
+    spec[9..].copy_from_slice(&area.addr()?);
+    Ok(spec)
+}
+
+/// transport size of the item spec and bytes per element when writing `area`
+fn write_transport_size(area: &Area) -> (u8, usize) {
+    match **area {
+        DataSizeType::Bit { .. } => (area.data(), 1),
+        DataSizeType::Counter { .. } | DataSizeType::Timer { .. } => (area.data(), 2),
+        _ => (constant::WL_BYTE, 1),
+    }
+}
+
+/// length field of a data item, in bits unless the data is an octet string, real or bit
+fn data_item_length(transport_size: u8, size: usize) -> u16 {
+    match transport_size as i32 {
+        constant::TS_RES_OCTET | constant::TS_RES_REAL | constant::TS_RES_BIT => size as u16,
+        _ => (size << 3) as u16,
+    }
+}
+
+/// byte length of a response item, the length is in bits unless the data is an octet string, real or bit
+fn response_item_len(transport_size: u8, length: u16) -> usize {
+    match transport_size as i32 {
+        constant::TS_RES_OCTET | constant::TS_RES_REAL | constant::TS_RES_BIT => length as usize,
+        _ => (length >> 3) as usize,
+    }
 }
 
 impl<T: Transport> Client<T> {
@@ -550,156 +862,269 @@ impl<T: Transport> Client<T> {
         CpuStatus::from_u8(response[44])
     }
 
+    /// communication capabilities, SZL 0x0131 index 1
     pub fn cp_info(&mut self) -> Result<CPInfo, Error> {
-        let szl = self.read_szl(0x0131, 0x000)?;
-
-        Ok(CPInfo {
-            max_pdu_length: BigEndian::read_u16(szl.data[2..].as_ref()),
-            max_connections: BigEndian::read_u16(szl.data[4..].as_ref()),
-            max_mpi_rate: BigEndian::read_u16(szl.data[6..].as_ref()),
-            max_bus_rate: BigEndian::read_u16(szl.data[10..].as_ref()),
+        let szl = self.read_szl(szl::SZL_ID_COMMUNICATION_CAPABILITIES, 0x0001)?;
+        for capabilities in CommunicationCapabilities::decode(&szl)? {
+            if let CommunicationCapabilities::General {
+                max_pdu_length,
+                max_connections,
+                max_mpi_rate,
+                max_bus_rate,
+            } = capabilities
+            {
+                return Ok(CPInfo {
+                    max_pdu_length,
+                    max_connections,
+                    max_mpi_rate,
+                    max_bus_rate,
+                });
+            }
+        }
+        Err(Error::InvalidResponse {
+            reason: "SZL 0x0131 without the general communication data".to_string(),
+            bytes: szl.data,
         })
     }
 
-    /// get cpu info
+    /// get cpu info, SZL 0x001C and 0x0011
     pub fn cpu_info(&mut self) -> Result<CpuInfo, Error> {
-        let szl = self.read_szl(0x001C, 0x000)?;
+        let component = self.component_identification()?;
+        let modules = self.module_identification()?;
+        Ok(CpuInfo::new(&component, &modules))
+    }
+
+    /// cpu info and communication capabilities
+    pub fn identity(&mut self) -> Result<Identity, Error> {
+        Ok(Identity {
+            cpu: self.cpu_info()?,
+            cp: self.cp_info()?,
+        })
+    }
 
-        if szl.data.len() < transport::SZL_MIN_RESPONSE {
+    /// read the partial list `id` with `index` of the system status list
+    ///
+    /// Lists larger than a pdu are sent in several fragments, every following fragment is
+    /// requested with the sequence number of the previous response.
+    pub fn read_szl(&mut self, id: u16, index: u16) -> Result<Szl, Error> {
+        let mut seq_out: u16 = 1;
+        let mut request = transport::SZL_FIRST_TELEGRAM.to_vec();
+        BigEndian::write_u16(request[11..].as_mut(), seq_out);
+        BigEndian::write_u16(request[29..].as_mut(), id);
+        BigEndian::write_u16(request[31..].as_mut(), index);
+
+        let response = self.transport.send(request.as_ref())?;
+        let (payload, mut done, mut seq_in) = szl_fragment(response.as_ref())?;
+        // the first fragment starts with ID, index, record length and number of records
+        if payload.len() < transport::SZL_HEADER_SIZE {
             return Err(Error::Response {
                 code: error::ISO_INVALID_PDU,
             });
         }
-
-        let module_type_name = match str::from_utf8(szl.data[172..204].as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(Error::InvalidResponse {
-                    bytes: szl.data[172..204].to_vec(),
-                    reason: e.to_string(),
-                })
-            }
+        let header = SzlHeader {
+            id: BigEndian::read_u16(payload[0..].as_ref()),
+            index: BigEndian::read_u16(payload[2..].as_ref()),
+            length_header: BigEndian::read_u16(payload[4..].as_ref()),
+            number_of_data_record: BigEndian::read_u16(payload[6..].as_ref()),
         };
+        let total = header.length_header as usize * header.number_of_data_record as usize;
+        let mut data = payload[transport::SZL_HEADER_SIZE..].to_vec();
 
-        let serial_number = match str::from_utf8(szl.data[138..162].as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(Error::InvalidResponse {
-                    bytes: szl.data[138..162].to_vec(),
-                    reason: e.to_string(),
-                })
-            }
-        };
-
-        let as_name = match str::from_utf8(szl.data[2..26].as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(Error::InvalidResponse {
-                    bytes: szl.data[2..26].to_vec(),
-                    reason: e.to_string(),
-                })
-            }
-        };
-
-        let copyright = match str::from_utf8(szl.data[104..130].as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(Error::InvalidResponse {
-                    bytes: szl.data[104..130].to_vec(),
-                    reason: e.to_string(),
-                })
-            }
-        };
-
-        let module_name = match str::from_utf8(szl.data[36..60].as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(Error::InvalidResponse {
-                    bytes: szl.data[36..60].to_vec(),
-                    reason: e.to_string(),
-                })
+        while !done {
+            seq_out += 1;
+            let mut request = transport::SZL_NEXT_TELEGRAM.to_vec();
+            BigEndian::write_u16(request[11..].as_mut(), seq_out);
+            request[24] = seq_in;
+
+            let response = self.transport.send(request.as_ref())?;
+            let (payload, last, seq) = szl_fragment(response.as_ref())?;
+            // a fragment without data would request the same fragment forever
+            if payload.is_empty() && !last {
+                return Err(Error::Response {
+                    code: error::CLI_INVALID_DATA_SIZE_RECVD,
+                });
             }
-        };
+            data.extend_from_slice(payload);
+            done = last;
+            seq_in = seq;
+        }
 
-        Ok(CpuInfo {
-            module_type_name: module_type_name.to_string(),
-            serial_number: serial_number.to_string(),
-            as_name: as_name.to_string(),
-            copyright: copyright.to_string(),
-            module_name: module_name.to_string(),
-        })
+        if data.len() < total {
+            return Err(Error::Response {
+                code: error::CLI_INVALID_DATA_SIZE_RECVD,
+            });
+        }
+        data.truncate(total);
+        Ok(Szl { header, data })
     }
 
-    fn read_szl(&mut self, id: u16, index: u16) -> Result<transport::S7SZL, Error> {
-        // let data_szl = 0;
-        let mut offset = 0;
-        let seq_out: u16 = 0x0000;
-
-        let mut s7_szlfirst = transport::SZL_FIRST_TELEGRAM.to_vec();
+    /// read the SZL-IDs of all partial lists the CPU supports
+    pub fn read_szl_list(&mut self) -> Result<Vec<u16>, Error> {
+        let szl = self.read_szl(szl::SZL_ID_LIST, 0x0000)?;
+        Ok(szl.records().map(BigEndian::read_u16).collect())
+    }
 
-        BigEndian::write_u16(s7_szlfirst[11..].as_mut(), seq_out + 1);
-        BigEndian::write_u16(s7_szlfirst[29..].as_mut(), id);
-        BigEndian::write_u16(s7_szlfirst[31..].as_mut(), index);
+    /// order number and versions of the module, SZL 0x0011
+    pub fn module_identification(&mut self) -> Result<Vec<ModuleIdentification>, Error> {
+        let szl = self.read_szl(szl::SZL_ID_MODULE_IDENTIFICATION, 0x0000)?;
+        ModuleIdentification::decode(&szl)
+    }
 
-        let mut res = self.transport.send(s7_szlfirst.as_ref())?;
+    /// the record `index` of the module identification, SZL 0x0111
+    ///
+    /// The indexes are the constants of `ModuleIdentification`, ie `INDEX_FIRMWARE`.
+    pub fn module_identification_record(
+        &mut self,
+        index: u16,
+    ) -> Result<ModuleIdentification, Error> {
+        let szl = self.read_szl(szl::SZL_ID_MODULE_IDENTIFICATION_RECORD, index)?;
+        ModuleIdentification::decode(&szl)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::InvalidResponse {
+                reason: format!("module identification {:#06X} has no records", index),
+                bytes: szl.data.clone(),
+            })
+    }
 
-        let validate = |res: &[u8], size: usize| -> Result<(), Error> {
-            if res.len() < transport::MIN_SZL_FIRST_TELEGRAM + size {
-                return Err(Error::Response {
-                    code: error::ISO_INVALID_PDU,
-                });
-            }
+    /// identification of all components, SZL 0x001C
+    pub fn component_identification(&mut self) -> Result<ComponentIdentification, Error> {
+        let szl = self.read_szl(szl::SZL_ID_COMPONENT_IDENTIFICATION, 0x0000)?;
+        ComponentIdentification::decode(&szl)
+    }
 
-            if BigEndian::read_u16(res[27..].as_ref()) != 0 && res[29] != 0xFF {
-                return Err(Error::CPU {
-                    code: error::CLI_INVALID_PLC_ANSWER,
-                });
+    /// communication capabilities of all classes the CPU provides, SZL 0x0131
+    ///
+    /// The classes 1 to 8 are read one by one, classes the CPU rejects are left out.
+    pub fn communication_capabilities(&mut self) -> Result<Vec<CommunicationCapabilities>, Error> {
+        let mut capabilities = Vec::new();
+        for index in 1..=8 {
+            match self.read_szl(szl::SZL_ID_COMMUNICATION_CAPABILITIES, index) {
+                Ok(szl) => capabilities.extend(CommunicationCapabilities::decode(&szl)?),
+                Err(Error::CPU { .. }) => {}
+                Err(err) => return Err(err),
             }
-            Ok(())
-        };
+        }
+        Ok(capabilities)
+    }
 
-        validate(res.as_ref(), 0)?;
+    /// protection level and mode selector, SZL 0x0232
+    pub fn protection(&mut self) -> Result<Protection, Error> {
+        let szl = self.read_szl(szl::SZL_ID_PROTECTION, Protection::INDEX)?;
+        Protection::decode(&szl)
+    }
 
-        // Skips extra params (ID, Index ...)
-        let mut data_szl = BigEndian::read_u16(res[31..].as_ref()) - 8;
+    /// current mode and the last mode transition, SZL 0x0424
+    pub fn mode_transition(&mut self) -> Result<ModeTransition, Error> {
+        let szl = self.read_szl(szl::SZL_ID_MODE_TRANSITION, 0x0000)?;
+        ModeTransition::decode(&szl)
+    }
 
-        validate(res.as_ref(), data_szl as usize)?;
+    /// status of the LEDs of the CPU, SZL 0x0019
+    pub fn leds(&mut self) -> Result<Vec<Led>, Error> {
+        let szl = self.read_szl(szl::SZL_ID_LEDS, 0x0000)?;
+        Led::decode(&szl)
+    }
 
-        let mut done = res[26] == 0x00;
-        // Slice sequence
-        let mut seq_in: u8 = res[24];
-        let header = transport::SZLHeader {
-            length_header: BigEndian::read_u16(res[37..].as_ref()) * 2,
-            number_of_data_record: BigEndian::read_u16(res[39..].as_ref()),
-        };
+    /// status of the module LEDs, SZL 0x0074
+    pub fn module_leds(&mut self) -> Result<Vec<Led>, Error> {
+        let szl = self.read_szl(szl::SZL_ID_MODULE_LEDS, 0x0000)?;
+        Led::decode(&szl)
+    }
 
-        let len = (offset + data_szl) as usize;
-        let mut data = vec![0u8; len];
+    /// status of all modules in the rack `rack`, SZL 0x0D91
+    pub fn rack_module_status(&mut self, rack: u16) -> Result<Vec<ModuleStatus>, Error> {
+        let szl = self.read_szl(szl::SZL_ID_RACK_MODULE_STATUS, rack)?;
+        ModuleStatus::decode(&szl)
+    }
 
-        data[offset as usize..len].copy_from_slice(res[41..41 + data_szl as usize].as_ref());
+    /// all entries of the diagnostic buffer, newest first
+    pub fn diagnostic_buffer(&mut self) -> Result<Vec<DiagnosticEntry>, Error> {
+        let szl = self.read_szl(diagnostic::SZL_ID_DIAGNOSTIC_BUFFER, 0x0000)?;
+        DiagnosticEntry::decode(&szl)
+    }
 
-        let mut szl = transport::S7SZL { header, data };
-        offset += data_szl;
+    /// the entries of the diagnostic buffer newer than `seen`, newest first
+    ///
+    /// All entries are returned when `seen` is no longer in the buffer.
+    pub fn diagnostic_buffer_since(
+        &mut self,
+        seen: &DiagnosticEntry,
+    ) -> Result<Vec<DiagnosticEntry>, Error> {
+        let entries = self.diagnostic_buffer()?;
+        Ok(DiagnosticEntry::newer_than(&entries, seen).to_vec())
+    }
 
-        let mut s7szlnext: Vec<u8> = transport::SZL_NEXT_TELEGRAM.to_vec();
+    /// read the real time clock of the PLC
+    ///
+    /// The year is completed with the century the PLC returns, years 90 to 99 are taken
+    /// as 19xx and all others as 20xx when the century is 0.
+    pub fn get_plc_datetime(&mut self) -> Result<DateTime, Error> {
+        let response = self
+            .transport
+            .send(transport::GET_CLOCK_TELEGRAM.as_ref())?;
+        check_userdata(response.as_ref())?;
+        if response.len() < transport::GET_CLOCK_TIME + 8 {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+            });
+        }
+        if response[29] != 0xFF {
+            return Err(Error::Response {
+                code: error::CLI_INVALID_PLC_ANSWER,
+            });
+        }
+        let century = value::bcd_byte(response[transport::GET_CLOCK_TIME - 1])? as u16;
+        let time = &response[transport::GET_CLOCK_TIME..transport::GET_CLOCK_TIME + 8];
+        let time = DateTime::from_bcd_bytes(time.try_into().unwrap())?;
+        if century == 0 {
+            return Ok(time);
+        }
+        DateTime::new(
+            century * 100 + time.year % 100,
+            time.month,
+            time.day,
+            time.hour,
+            time.minute,
+            time.second,
+            time.nanosecond,
+        )
+    }
 
-        while !done {
-            BigEndian::write_u16(s7_szlfirst[11..].as_mut(), seq_out + 1);
-            s7szlnext[24] = seq_in;
+    /// set the real time clock of the PLC, years 1990 to 2089 are supported
+    pub fn set_plc_datetime(&mut self, time: &DateTime) -> Result<(), Error> {
+        let mut request = transport::SET_CLOCK_TELEGRAM.to_vec();
+        request[transport::SET_CLOCK_CENTURY] = value::to_bcd_byte((time.year / 100) as u8);
+        request[transport::SET_CLOCK_CENTURY + 1..].copy_from_slice(&time.to_bcd_bytes()?);
 
-            res = self.transport.send(s7szlnext.as_ref())?;
+        let response = self.transport.send(request.as_ref())?;
+        check_userdata(response.as_ref())
+    }
 
-            validate(res.as_ref(), 0)?;
+    /// set the real time clock of the PLC to the system time of the host
+    ///
+    /// The system time is set as UTC, use `set_plc_datetime` for PLCs running on local time.
+    pub fn set_plc_system_datetime(&mut self) -> Result<(), Error> {
+        self.set_plc_datetime(&DateTime::from_system_time(SystemTime::now())?)
+    }
 
-            data_szl = BigEndian::read_u16(res[31..].as_ref());
-            done = res[26] == 0x00;
-            seq_in = res[24];
+    /// send the password of a protected CPU, it is valid until the connection is closed
+    ///
+    /// Passwords have up to 8 characters. `Error::InvalidPassword` is returned when the CPU
+    /// rejects it and `Error::NoPasswordToSetOrClear` when the CPU is not protected.
+    pub fn set_session_password(&mut self, password: &str) -> Result<(), Error> {
+        let mut request = transport::SET_PASSWORD_TELEGRAM.to_vec();
+        request[transport::SET_PASSWORD_DATA..].copy_from_slice(&encode_password(password)?);
+        let response = self.transport.send(request.as_ref())?;
+        check_userdata(response.as_ref()).map_err(password_error)
+    }
 
-            szl.data = vec![0u8; len];
-            offset += data_szl;
-            szl.header.length_header += szl.header.length_header;
-        }
-        Ok(szl)
+    /// clear the session password set with `set_session_password`
+    pub fn clear_session_password(&mut self) -> Result<(), Error> {
+        let response = self
+            .transport
+            .send(transport::CLEAR_PASSWORD_TELEGRAM.as_ref())?;
+        check_userdata(response.as_ref()).map_err(password_error)
     }
 
     fn cold_warm_start_stop(
@@ -727,3 +1152,1074 @@ impl<T: Transport> Client<T> {
         Ok(())
     }
 }
+
+/// pads the password with spaces to 8 bytes and obfuscates it like the PLC expects
+fn encode_password(password: &str) -> Result<[u8; 8], Error> {
+    if password.is_empty() || password.len() > 8 || !password.is_ascii() {
+        return Err(Error::InvalidInput {
+            input: "the password has to be 1 to 8 ASCII characters".to_string(),
+        });
+    }
+    let mut encoded = [b' '; 8];
+    encoded[..password.len()].copy_from_slice(password.as_bytes());
+    encoded[0] ^= 0x55;
+    encoded[1] ^= 0x55;
+    for i in 2..8 {
+        encoded[i] ^= 0x55 ^ encoded[i - 2];
+    }
+    Ok(encoded)
+}
+
+fn password_error(err: Error) -> Error {
+    match err {
+        Error::CPU { code } => Error::password(code),
+        err => err,
+    }
+}
+
+/// checks the error code in the parameters of a userdata response
+fn check_userdata(response: &[u8]) -> Result<(), Error> {
+    if response.len() < transport::USERDATA_MIN_RESPONSE {
+        return Err(Error::Response {
+            code: error::ISO_INVALID_PDU,
+        });
+    }
+    let code = BigEndian::read_u16(response[27..].as_ref());
+    if code != 0 {
+        return Err(Error::CPU { code: code as i32 });
+    }
+    Ok(())
+}
+
+/// checks a SZL response fragment, returns its data, whether it is the last fragment
+/// and its sequence number
+fn szl_fragment(response: &[u8]) -> Result<(&[u8], bool, u8), Error> {
+    check_userdata(response)?;
+    if response.len() < transport::SZL_DATA_OFFSET {
+        return Err(Error::Response {
+            code: error::ISO_INVALID_PDU,
+        });
+    }
+    if response[29] != 0xFF {
+        return Err(Error::Response {
+            code: error::CLI_INVALID_PLC_ANSWER,
+        });
+    }
+    let len = BigEndian::read_u16(response[31..].as_ref()) as usize;
+    let end = transport::SZL_DATA_OFFSET + len;
+    if response.len() < end {
+        return Err(Error::Response {
+            code: error::CLI_INVALID_DATA_SIZE_RECVD,
+        });
+    }
+    Ok((
+        &response[transport::SZL_DATA_OFFSET..end],
+        response[26] == 0x00,
+        response[24],
+    ))
+}
+
+#[cfg(test)]
+struct MockTransport {
+    pdu_length: u16,
+    requests: Vec<Vec<u8>>,
+    responses: std::collections::VecDeque<Vec<u8>>,
+}
+
+#[cfg(test)]
+impl MockTransport {
+    fn client(pdu_length: u16, responses: Vec<Vec<u8>>) -> Client<MockTransport> {
+        Client::new(MockTransport {
+            pdu_length,
+            requests: Vec::new(),
+            responses: responses.into(),
+        })
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Transport for MockTransport {
+    fn send(&mut self, request: &[u8]) -> Result<Vec<u8>, Error> {
+        self.requests.push(request.to_vec());
+        self.responses.pop_front().ok_or(Error::Send)
+    }
+
+    fn pdu_length(&self) -> u16 {
+        self.pdu_length
+    }
+
+    fn negotiate(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn connection_type(&self) -> transport::Connection {
+        transport::Connection::PG
+    }
+}
+
+#[cfg(test)]
+fn write_ack(return_code: u8) -> Vec<u8> {
+    let mut ack = vec![
+        3, 0, 0, 22, 2, 240, 128, 50, 3, 0, 0, 5, 0, 0, 2, 0, 1, 0, 0, 5, 1,
+    ];
+    ack.push(return_code);
+    ack
+}
+
+#[test]
+fn test_write_bytes() {
+    let mut cl = MockTransport::client(480, vec![write_ack(0xFF)]);
+    let area = Area::DataBausteine(1, DataSizeType::Byte { addr: 10, len: 4 });
+    cl.write(area, &[1, 2, 3, 4]).unwrap();
+
+    let request = &cl.transport.requests[0];
+    assert_eq!(request.len(), 39);
+    assert_eq!(BigEndian::read_u16(&request[2..]), 39);
+    assert_eq!(BigEndian::read_u16(&request[15..]), 8);
+    assert_eq!(request[17], 0x05);
+    assert_eq!(request[22], constant::WL_BYTE);
+    assert_eq!(BigEndian::read_u16(&request[23..]), 4);
+    assert_eq!(BigEndian::read_u16(&request[25..]), 1);
+    assert_eq!(request[27], 0x84);
+    assert_eq!(request[28..31], [0, 0, 80]);
+    assert_eq!(request[32], constant::TS_RES_BYTE as u8);
+    assert_eq!(BigEndian::read_u16(&request[33..]), 32);
+    assert_eq!(request[35..], [1, 2, 3, 4]);
+}
+
+#[test]
+fn test_write_bit() {
+    let mut cl = MockTransport::client(480, vec![write_ack(0xFF)]);
+    let area = Area::ProcessOutput(DataSizeType::Bit {
+        addr: 2,
+        bit_addr: constant::BitAddr::Addr5,
+    });
+    cl.write(area, &[1]).unwrap();
+
+    let request = &cl.transport.requests[0];
+    assert_eq!(request[22], 0x01);
+    assert_eq!(request[27], 0x82);
+    assert_eq!(request[28..31], [0, 0, 21]);
+    assert_eq!(request[32], constant::TS_RES_BIT as u8);
+    assert_eq!(BigEndian::read_u16(&request[33..]), 1);
+    assert_eq!(request[35..], [1]);
+}
+
+#[test]
+fn test_write_split_by_pdu() {
+    let mut cl = MockTransport::client(40, vec![write_ack(0xFF); 3]);
+    let area = Area::DataBausteine(2, DataSizeType::Byte { addr: 0, len: 12 });
+    let data: Vec<u8> = (0..12).collect();
+    cl.write(area, &data).unwrap();
+
+    let requests = &cl.transport.requests;
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0][28..31], [0, 0, 0]);
+    assert_eq!(requests[0][35..], [0, 1, 2, 3, 4]);
+    assert_eq!(requests[1][28..31], [0, 0, 40]);
+    assert_eq!(requests[1][35..], [5, 6, 7, 8, 9]);
+    assert_eq!(requests[2][28..31], [0, 0, 80]);
+    assert_eq!(requests[2][35..], [10, 11]);
+}
+
+#[test]
+fn test_write_errors() {
+    let mut cl = MockTransport::client(480, vec![write_ack(0x05)]);
+    let area = Area::DataBausteine(1, DataSizeType::Word { addr: 0, len: 2 });
+
+    match cl.write(area, &[0; 3]) {
+        Err(Error::InvalidInput { .. }) => {}
+        other => panic!("expected invalid input got {:?}", other),
+    }
+    assert!(cl.transport.requests.is_empty());
+
+    match cl.write(area, &[0; 4]) {
+        Err(Error::CPU { code }) => assert_eq!(code, 0x05),
+        other => panic!("expected cpu error got {:?}", other),
+    }
+}
+
+#[cfg(test)]
+fn read_multi_response(items: &[(u8, u8, &[u8])]) -> Vec<u8> {
+    let mut response = vec![
+        3, 0, 0, 0, 2, 240, 128, 50, 3, 0, 0, 5, 0, 0, 2, 0, 0, 0, 0, 4,
+    ];
+    response.push(items.len() as u8);
+    for (n, (code, transport_size, data)) in items.iter().enumerate() {
+        let length = if *transport_size as i32 == constant::TS_RES_BYTE {
+            data.len() << 3
+        } else {
+            data.len()
+        };
+        response.extend_from_slice(&[*code, *transport_size, (length >> 8) as u8, length as u8]);
+        response.extend_from_slice(data);
+        if data.len() % 2 == 1 && n + 1 < items.len() {
+            response.push(0);
+        }
+    }
+    let size = response.len() as u16;
+    BigEndian::write_u16(response[2..].as_mut(), size);
+    response
+}
+
+#[test]
+fn test_read_multi() {
+    let ts_byte = constant::TS_RES_BYTE as u8;
+    let response = read_multi_response(&[
+        (0xFF, ts_byte, &[1, 2, 3]),
+        (0x0A, 0, &[]),
+        (0xFF, constant::TS_RES_BIT as u8, &[1]),
+        (0xFF, constant::TS_RES_REAL as u8, &[66, 86, 0, 0]),
+    ]);
+    let mut cl = MockTransport::client(480, vec![response]);
+    let areas = [
+        Area::DataBausteine(1, DataSizeType::Byte { addr: 0, len: 3 }),
+        Area::DataBausteine(2, DataSizeType::Word { addr: 4, len: 1 }),
+        Area::ProcessInput(DataSizeType::Bit {
+            addr: 1,
+            bit_addr: constant::BitAddr::Addr3,
+        }),
+        Area::V(DataSizeType::Real { addr: 10, len: 1 }),
+    ];
+    let results = cl.read_multi(&areas).unwrap();
+
+    let request = &cl.transport.requests[0];
+    assert_eq!(request.len(), 19 + 4 * ITEM_SPEC_SIZE);
+    assert_eq!(BigEndian::read_u16(&request[13..]), 2 + 4 * 12);
+    assert_eq!(request[17], 0x04);
+    assert_eq!(request[18], 4);
+    assert_eq!(request[31..43], item_spec(&areas[1], 0x04, 1).unwrap());
+    assert_eq!(request[43 + 9..43 + 12], [0, 0, 11]);
+
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap(), &vec![1, 2, 3]);
+    match &results[1] {
+        Err(Error::CPU { code }) => assert_eq!(*code, 0x0A),
+        other => panic!("expected cpu error got {:?}", other),
+    }
+    assert_eq!(results[2].as_ref().unwrap(), &vec![1]);
+    assert_eq!(results[3].as_ref().unwrap(), &vec![66, 86, 0, 0]);
+}
+
+#[test]
+fn test_read_multi_split_jobs() {
+    let ts_byte = constant::TS_RES_BYTE as u8;
+    let areas: Vec<Area> = (0..25)
+        .map(|i| Area::DataBausteine(1, DataSizeType::Byte { addr: i, len: 1 }))
+        .collect();
+    let first: Vec<(u8, u8, &[u8])> = (0..20).map(|_| (0xFF, ts_byte, &[7u8][..])).collect();
+    let second: Vec<(u8, u8, &[u8])> = (0..5).map(|_| (0xFF, ts_byte, &[8u8][..])).collect();
+    let mut cl = MockTransport::client(
+        480,
+        vec![read_multi_response(&first), read_multi_response(&second)],
+    );
+    let results = cl.read_multi(&areas).unwrap();
+
+    assert_eq!(cl.transport.requests.len(), 2);
+    assert_eq!(cl.transport.requests[0][18], constant::MAX_VARS as u8);
+    assert_eq!(cl.transport.requests[1][18], 5);
+    assert_eq!(results.len(), 25);
+    assert_eq!(results[19].as_ref().unwrap(), &vec![7]);
+    assert_eq!(results[20].as_ref().unwrap(), &vec![8]);
+
+    // the response of two 10 byte items does not fit into a 40 byte pdu
+    let areas = [
+        Area::DataBausteine(1, DataSizeType::Byte { addr: 0, len: 10 }),
+        Area::DataBausteine(1, DataSizeType::Byte { addr: 20, len: 10 }),
+    ];
+    let data = [0u8; 10];
+    let mut cl = MockTransport::client(
+        40,
+        vec![
+            read_multi_response(&[(0xFF, ts_byte, &data)]),
+            read_multi_response(&[(0xFF, ts_byte, &data)]),
+        ],
+    );
+    let results = cl.read_multi(&areas).unwrap();
+    assert_eq!(cl.transport.requests.len(), 2);
+    assert!(results.iter().all(|r| r.is_ok()));
+}
+
+#[test]
+fn test_write_multi() {
+    let mut ack = vec![
+        3, 0, 0, 24, 2, 240, 128, 50, 3, 0, 0, 5, 0, 0, 2, 0, 3, 0, 0, 5, 3,
+    ];
+    ack.extend_from_slice(&[0xFF, 0x05, 0xFF]);
+    let mut cl = MockTransport::client(480, vec![ack]);
+    let items = vec![
+        (
+            Area::DataBausteine(1, DataSizeType::Byte { addr: 0, len: 3 }),
+            vec![1, 2, 3],
+        ),
+        (
+            Area::DataBausteine(2, DataSizeType::Int { addr: 8, len: 1 }),
+            vec![0, 9],
+        ),
+        (
+            Area::ProcessOutput(DataSizeType::Bit {
+                addr: 0,
+                bit_addr: constant::BitAddr::Addr1,
+            }),
+            vec![1],
+        ),
+    ];
+    let err = cl.write_multi(&items).unwrap_err();
+    assert_eq!(err.code(), Some(error::CLI_PARTIAL_DATA_WRITTEN));
+    let results = match err {
+        Error::PartialWrite { results } => results,
+        other => panic!("expected a partial write got {:?}", other),
+    };
+
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    match &results[1] {
+        Err(e) => assert_eq!(e.code(), Some(error::CLI_ADDRESS_OUT_OF_RANGE)),
+        other => panic!("expected cpu error got {:?}", other),
+    }
+    assert!(results[2].is_ok());
+
+    let request = &cl.transport.requests[0];
+    assert_eq!(request.len(), BigEndian::read_u16(&request[2..]) as usize);
+    assert_eq!(request[17], 0x05);
+    assert_eq!(request[18], 3);
+    assert_eq!(BigEndian::read_u16(&request[13..]), 2 + 3 * 12);
+    // item specs
+    assert_eq!(
+        request[19..31],
+        item_spec(&items[0].0, constant::WL_BYTE, 3).unwrap()
+    );
+    assert_eq!(
+        request[31..43],
+        item_spec(&items[1].0, constant::WL_BYTE, 2).unwrap()
+    );
+    assert_eq!(request[43..55], item_spec(&items[2].0, 0x01, 1).unwrap());
+    // data items, the odd first item is padded
+    assert_eq!(BigEndian::read_u16(&request[15..]), 8 + 6 + 5);
+    assert_eq!(request[55..63], [0, 4, 0, 24, 1, 2, 3, 0]);
+    assert_eq!(request[63..69], [0, 4, 0, 16, 0, 9]);
+    assert_eq!(request[69..], [0, 3, 0, 1, 1]);
+
+    // invalid items fail the whole call before anything is sent
+    let mut cl = MockTransport::client(480, vec![]);
+    for invalid in [
+        (
+            Area::DataBausteine(3, DataSizeType::Word { addr: 0, len: 1 }),
+            vec![0],
+        ),
+        (
+            Area::LocalData(DataSizeType::Byte { addr: 0, len: 1 }),
+            vec![0],
+        ),
+    ] {
+        match cl.write_multi(&[items[0].clone(), invalid]) {
+            Err(Error::InvalidInput { .. }) => {}
+            other => panic!("expected invalid input got {:?}", other),
+        }
+    }
+    assert!(cl.transport.requests.is_empty());
+
+    // nothing is partial when every item is written or every item is rejected
+    let mut cl = MockTransport::client(480, vec![write_ack(0xFF)]);
+    cl.write_multi(&items[..1]).unwrap();
+    let mut cl = MockTransport::client(480, vec![write_ack(0x05)]);
+    match cl.write_multi(&items[1..2]) {
+        Err(e @ Error::CPU { .. }) => assert_eq!(e.code(), Some(error::CLI_ADDRESS_OUT_OF_RANGE)),
+        other => panic!("expected cpu error got {:?}", other),
+    }
+
+    // a failed job stops writing, the items of the first job fill the pdu
+    let mut cl = MockTransport::client(40, vec![write_ack(0xFF)]);
+    match cl.write_multi(&items) {
+        Err(Error::Send) => {}
+        other => panic!("expected a send error got {:?}", other),
+    }
+    assert_eq!(cl.transport.requests.len(), 2);
+}
+
+#[test]
+fn test_merker() {
+    let mut cl = MockTransport::client(
+        480,
+        vec![
+            read_multi_response(&[(0xFF, constant::TS_RES_BYTE as u8, &[0x12, 0x34])]),
+            write_ack(0xFF),
+        ],
+    );
+    let area = Area::Merker(DataSizeType::Word { addr: 100, len: 1 });
+    assert_eq!(cl.read(area).unwrap(), vec![0x12, 0x34]);
+    cl.write(area, &[0x56, 0x78]).unwrap();
+
+    for request in cl.transport.requests.iter() {
+        assert_eq!(BigEndian::read_u16(&request[25..]), 0);
+        assert_eq!(request[27], 0x83);
+        assert_eq!(request[28..31], [0, 3, 32]);
+    }
+}
+
+#[test]
+fn test_tags() {
+    let mut cl = MockTransport::client(
+        480,
+        vec![
+            read_multi_response(&[(0xFF, constant::TS_RES_BYTE as u8, &[0xFF, 0xFE])]),
+            write_ack(0xFF),
+        ],
+    );
+    assert!(cl.read_tag("Motor1_Speed").is_err());
+    cl.set_tags(TagTable::from_sdf("\"Motor1_Speed\",\"MW   100\",\"INT\",\"rpm\"").unwrap());
+    assert_eq!(cl.read_tag("Motor1_Speed").unwrap(), S7Value::Int(-2));
+    cl.write_tag("Motor1_Speed", S7Value::Int(300)).unwrap();
+    assert_eq!(cl.transport.requests[1][35..], [0x01, 0x2C]);
+    assert!(cl.write_tag("Motor1_Speed", S7Value::Real(1.0)).is_err());
+}
+
+#[test]
+fn test_timer_counter() {
+    let mut cl = MockTransport::client(
+        40,
+        vec![
+            read_multi_response(&[(0xFF, constant::TS_RES_OCTET as u8, &[0x21, 0x27, 0, 0x50])]),
+            write_ack(0xFF),
+            write_ack(0xFF),
+        ],
+    );
+    let timers = Area::Timer(DataSizeType::Timer { addr: 5, len: 2 });
+    assert_eq!(cl.read(timers).unwrap(), vec![0x21, 0x27, 0, 0x50]);
+    let request = &cl.transport.requests[0];
+    assert_eq!(request[22], 0x1D);
+    assert_eq!(BigEndian::read_u16(&request[23..]), 2);
+    assert_eq!(request[27], 0x1D);
+    assert_eq!(request[28..31], [0, 0, 5]);
+
+    // 40 byte pdu holds 2 counters per job
+    let counters = Area::Counter(DataSizeType::Counter { addr: 3, len: 3 });
+    cl.write(counters, &[0, 1, 0, 2, 0, 3]).unwrap();
+    let request = &cl.transport.requests[1];
+    assert_eq!(request[22], 0x1C);
+    assert_eq!(BigEndian::read_u16(&request[23..]), 2);
+    assert_eq!(request[27], 0x1C);
+    assert_eq!(request[28..31], [0, 0, 3]);
+    assert_eq!(request[32], constant::TS_RES_OCTET as u8);
+    assert_eq!(BigEndian::read_u16(&request[33..]), 4);
+    let request = &cl.transport.requests[2];
+    assert_eq!(request[28..31], [0, 0, 5]);
+    assert_eq!(request[35..], [0, 3]);
+
+    let invalid = Area::Timer(DataSizeType::Word { addr: 0, len: 1 });
+    assert!(cl.read(invalid).is_err());
+    let invalid = Area::Merker(DataSizeType::Counter { addr: 0, len: 1 });
+    assert!(cl.write(invalid, &[0, 0]).is_err());
+    assert_eq!(cl.transport.requests.len(), 3);
+}
+
+#[test]
+fn test_extended_areas() {
+    let mut cl = MockTransport::client(
+        480,
+        vec![
+            read_multi_response(&[(0xFF, constant::TS_RES_BYTE as u8, &[0, 1])]),
+            write_ack(0xFF),
+            write_ack(0xFF),
+        ],
+    );
+    let input = Area::PeripheralInput(DataSizeType::Word { addr: 256, len: 1 });
+    cl.read(input).unwrap();
+    let output = Area::PeripheralOutput(DataSizeType::Word { addr: 256, len: 1 });
+    cl.write(output, &[0, 1]).unwrap();
+    let instance = Area::InstanceData(7, DataSizeType::Byte { addr: 2, len: 1 });
+    cl.write(instance, &[3]).unwrap();
+
+    let requests = &cl.transport.requests;
+    assert_eq!(requests[0][27], 0x80);
+    assert_eq!(requests[1][27], 0x80);
+    assert_eq!(BigEndian::read_u16(&requests[2][25..]), 7);
+    assert_eq!(requests[2][27], 0x85);
+
+    for read_only in [
+        Area::LocalData(DataSizeType::Byte { addr: 0, len: 1 }),
+        Area::PreviousLocalData(DataSizeType::Byte { addr: 0, len: 1 }),
+        input,
+    ] {
+        match cl.write(read_only, &[0, 0]) {
+            Err(Error::InvalidInput { .. }) => {}
+            other => panic!("expected invalid input got {:?}", other),
+        }
+    }
+    match cl.read(output) {
+        Err(Error::InvalidInput { .. }) => {}
+        other => panic!("expected invalid input got {:?}", other),
+    }
+    assert_eq!(cl.transport.requests.len(), 3);
+}
+
+#[test]
+fn test_read_split_by_pdu() {
+    let ts_byte = constant::TS_RES_BYTE as u8;
+    let data: Vec<u8> = (0..50).collect();
+    let mut cl = MockTransport::client(
+        40,
+        vec![
+            read_multi_response(&[(0xFF, ts_byte, &data[..22])]),
+            read_multi_response(&[(0xFF, ts_byte, &data[22..44])]),
+            read_multi_response(&[(0xFF, ts_byte, &data[44..])]),
+        ],
+    );
+    let area = Area::DataBausteine(3, DataSizeType::Word { addr: 100, len: 25 });
+    assert_eq!(cl.read(area).unwrap(), data);
+
+    let requests = &cl.transport.requests;
+    assert_eq!(requests.len(), 3);
+    assert_eq!(BigEndian::read_u16(&requests[0][23..]), 11);
+    assert_eq!(BigEndian::read_u16(&requests[1][23..]), 11);
+    assert_eq!(BigEndian::read_u16(&requests[2][23..]), 3);
+    assert_eq!(requests[0][28..31], area.addr_at(0).unwrap());
+    assert_eq!(requests[1][28..31], area.addr_at(22).unwrap());
+    assert_eq!(requests[2][28..31], area.addr_at(44).unwrap());
+    assert_eq!(requests[2][28..31], [0, 0x04, 0x80]);
+
+    // a short answer is rejected
+    let mut cl = MockTransport::client(480, vec![read_multi_response(&[(0xFF, ts_byte, &[1])])]);
+    let area = Area::DataBausteine(3, DataSizeType::Byte { addr: 0, len: 2 });
+    assert!(cl.read(area).is_err());
+}
+
+#[test]
+fn test_large_addresses() {
+    let area = Area::DataBausteine(
+        1,
+        DataSizeType::Bit {
+            addr: constant::MAX_BYTE_ADDR,
+            bit_addr: constant::BitAddr::Addr7,
+        },
+    );
+    assert_eq!(area.addr().unwrap(), [0xFF, 0xFF, 0xFF]);
+    let area = Area::DataBausteine(
+        1,
+        DataSizeType::Byte {
+            addr: constant::MAX_BYTE_ADDR,
+            len: 2,
+        },
+    );
+    match area.addr_at(1) {
+        Err(Error::InvalidAddr(addr)) => assert_eq!(addr, 0x200000),
+        other => panic!("expected invalid addr got {:?}", other),
+    }
+
+    let mut cl = MockTransport::client(480, vec![]);
+    assert!(cl.read(area).is_err());
+    assert!(cl.write(area, &[0, 0]).is_err());
+    assert!(cl.transport.requests.is_empty());
+
+    // 70000 bytes need 152 requests of at most 462 bytes
+    let ts_byte = constant::TS_RES_BYTE as u8;
+    let chunk = vec![1u8; 462];
+    let mut responses: Vec<Vec<u8>> = (0..151)
+        .map(|_| read_multi_response(&[(0xFF, ts_byte, &chunk)]))
+        .collect();
+    responses.push(read_multi_response(&[(0xFF, ts_byte, &chunk[..238])]));
+    let mut cl = MockTransport::client(480, responses);
+    let area = Area::DataBausteine(
+        1,
+        DataSizeType::Byte {
+            addr: 70000,
+            len: 70000,
+        },
+    );
+    assert_eq!(cl.read(area).unwrap().len(), 70000);
+    let last = cl.transport.requests.last().unwrap();
+    assert_eq!(BigEndian::read_u16(&last[23..]), 238);
+    assert_eq!(
+        last[28..31],
+        ((70000u32 + 151 * 462) << 3).to_be_bytes()[1..]
+    );
+}
+
+#[test]
+fn test_values() {
+    let mut cl = MockTransport::client(
+        480,
+        vec![
+            read_multi_response(&[(0xFF, constant::TS_RES_BYTE as u8, &[0x00, 0x00, 0x01, 0x00])]),
+            write_ack(0xFF),
+        ],
+    );
+    let area: Area = "DB1.DBD4".parse().unwrap();
+    assert_eq!(cl.read_value(area).unwrap(), S7Value::DWord(256));
+    let area = Area::DataBausteine(1, DataSizeType::DInt { addr: 4, len: 1 });
+    cl.write_values(area, &[S7Value::DInt(-1)]).unwrap();
+    assert_eq!(cl.transport.requests[1][35..], [0xFF; 4]);
+
+    assert!(cl.write_values(area, &[S7Value::Real(1.0)]).is_err());
+    let area = Area::DataBausteine(1, DataSizeType::DInt { addr: 4, len: 2 });
+    assert!(cl.read_value(area).is_err());
+    assert_eq!(cl.transport.requests.len(), 2);
+}
+
+#[test]
+fn test_load_store_fields() {
+    use crate::field::{Bool, Float, Int};
+
+    let ts_byte = constant::TS_RES_BYTE as u8;
+    let response = read_multi_response(&[
+        (0xFF, ts_byte, &[0, 7]),
+        (0xFF, ts_byte, &[66, 86, 0, 0, 0b1000]),
+        (0xFF, ts_byte, &[0xFF, 0xFF]),
+    ]);
+    let mut ack = vec![
+        3, 0, 0, 23, 2, 240, 128, 50, 3, 0, 0, 5, 0, 0, 2, 0, 2, 0, 0, 5, 2,
+    ];
+    ack.extend_from_slice(&[0xFF, 0xFF]);
+    let mut cl = MockTransport::client(480, vec![response, ack]);
+
+    let mut fields: Fields = vec![
+        Box::new(Float::new("DB1.DBD8".parse().unwrap(), vec![0; 4]).unwrap()),
+        Box::new(Bool::new("DB1.DBX12.3".parse().unwrap(), vec![0]).unwrap()),
+        Box::new(Int::new("DB1.DBW40".parse().unwrap(), vec![0; 2]).unwrap()),
+        Box::new(Int::new("MW4".parse().unwrap(), vec![0; 2]).unwrap()),
+    ];
+    let results = cl.load_fields(&mut fields).unwrap();
+    assert!(results.iter().all(|r| r.is_ok()));
+
+    // the float and the bool are read as one item, the distant int on its own
+    let request = &cl.transport.requests[0];
+    assert_eq!(request[18], 3);
+    let merker = Area::Merker(DataSizeType::Byte { addr: 4, len: 2 });
+    assert_eq!(request[19..31], item_spec(&merker, 0x02, 2).unwrap());
+    assert_eq!(BigEndian::read_u16(&request[31 + 4..]), 5);
+    assert_eq!(request[31 + 9..31 + 12], [0, 0, 64]);
+
+    assert_eq!(fields[0].downcast_ref::<Float>().unwrap().value(), 53.5);
+    assert!(fields[1].downcast_ref::<Bool>().unwrap().value());
+    assert_eq!(fields[2].downcast_ref::<Int>().unwrap().value(), -1);
+    assert_eq!(fields[3].downcast_ref::<Int>().unwrap().value(), 7);
+    assert!(fields.iter().all(|f| !f.is_dirty()));
+
+    // nothing changed, nothing is sent
+    let results = cl.store_fields(&mut fields).unwrap();
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert_eq!(cl.transport.requests.len(), 1);
+
+    fields[0].downcast_mut::<Float>().unwrap().set_value(53.5);
+    fields[1].downcast_mut::<Bool>().unwrap().set_value(false);
+    fields[3].downcast_mut::<Int>().unwrap().set_value(3);
+    let results = cl.store_fields(&mut fields).unwrap();
+    assert_eq!(results.len(), 4);
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert!(fields.iter().all(|f| !f.is_dirty()));
+
+    // only the single bit and MW4 are written
+    let request = &cl.transport.requests[1];
+    assert_eq!(request[18], 2);
+    assert_eq!(request[19 + 3], 0x01);
+    assert_eq!(request[19 + 9..19 + 12], [0, 0, 99]);
+    assert_eq!(request[31 + 3], constant::WL_BYTE);
+    assert_eq!(request[43..48], [0, constant::TS_RES_BIT as u8, 0, 1, 0]);
+    assert_eq!(
+        request[48..],
+        [0, 0, constant::TS_RES_BYTE as u8, 0, 16, 0, 3]
+    );
+
+    // the written fields are clean, a second store sends nothing
+    let results = cl.store_fields(&mut fields).unwrap();
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert_eq!(cl.transport.requests.len(), 2);
+    assert!(!fields[1].downcast_ref::<Bool>().unwrap().value());
+    assert_eq!(fields[3].downcast_ref::<Int>().unwrap().value(), 3);
+
+    // a rejected field stays dirty and is sent again
+    let mut ack = vec![
+        3, 0, 0, 23, 2, 240, 128, 50, 3, 0, 0, 5, 0, 0, 2, 0, 2, 0, 0, 5, 2,
+    ];
+    ack.extend_from_slice(&[0xFF, 0x05]);
+    cl.transport.responses.push_back(ack);
+    cl.transport.responses.push_back(write_ack(0xFF));
+    fields[2].downcast_mut::<Int>().unwrap().set_value(1);
+    fields[3].downcast_mut::<Int>().unwrap().set_value(4);
+    let results = cl.store_fields(&mut fields).unwrap();
+    assert!(results[2].is_ok());
+    assert!(results[3].is_err());
+    assert!(!fields[2].is_dirty());
+    assert!(fields[3].is_dirty());
+    cl.store_fields(&mut fields).unwrap();
+    assert_eq!(cl.transport.requests.len(), 4);
+    assert_eq!(cl.transport.requests[3][18], 1);
+    assert!(fields.iter().all(|f| !f.is_dirty()));
+}
+
+#[cfg(test)]
+fn szl_response(seq: u8, last: bool, payload: &[u8]) -> Vec<u8> {
+    let mut response = vec![
+        3,
+        0,
+        0,
+        0,
+        2,
+        240,
+        128,
+        50,
+        7,
+        0,
+        0,
+        0,
+        1,
+        0,
+        12,
+        0,
+        0,
+        0,
+        1,
+        18,
+        8,
+        18,
+        132,
+        1,
+        seq,
+        0,
+        !last as u8,
+        0,
+        0,
+        255,
+        9,
+        0,
+        0,
+    ];
+    BigEndian::write_u16(response[31..].as_mut(), payload.len() as u16);
+    response.extend_from_slice(payload);
+    let len = response.len() as u16;
+    BigEndian::write_u16(response[2..].as_mut(), len);
+    response
+}
+
+#[test]
+fn test_read_szl() {
+    let records: Vec<u8> = (0..84).collect();
+    let mut first = vec![0x00, 0x11, 0x00, 0x00, 0x00, 28, 0x00, 3];
+    first.extend_from_slice(&records[..50]);
+    let mut cl = MockTransport::client(
+        480,
+        vec![
+            szl_response(7, false, &first),
+            szl_response(8, false, &records[50..70]),
+            szl_response(9, true, &records[70..]),
+        ],
+    );
+    let szl = cl.read_szl(0x0011, 0x0000).unwrap();
+    assert_eq!(szl.header.id, 0x0011);
+    assert_eq!(szl.header.length_header, 28);
+    assert_eq!(szl.data, records);
+    assert_eq!(szl.records().count(), 3);
+    assert_eq!(szl.records().nth(2).unwrap(), &records[56..]);
+
+    let requests = &cl.transport.requests;
+    assert_eq!(requests[0][29..33], [0x00, 0x11, 0x00, 0x00]);
+    assert_eq!(requests[1][11..13], [0, 2]);
+    assert_eq!(requests[1][24], 7);
+    assert_eq!(requests[2][11..13], [0, 3]);
+    assert_eq!(requests[2][24], 8);
+}
+
+#[test]
+fn test_read_szl_list() {
+    let mut cl = MockTransport::client(
+        480,
+        vec![szl_response(
+            1,
+            true,
+            &[0, 0, 0, 0, 0, 2, 0, 3, 0x00, 0x11, 0x00, 0x1C, 0x01, 0x31],
+        )],
+    );
+    assert_eq!(cl.read_szl_list().unwrap(), vec![0x0011, 0x001C, 0x0131]);
+}
+
+#[test]
+fn test_read_szl_errors() {
+    let header = [0x00, 0x11, 0x00, 0x00, 0x00, 28, 0x00, 3];
+    // the records are truncated
+    let mut cl = MockTransport::client(480, vec![szl_response(1, true, &header)]);
+    assert_eq!(
+        cl.read_szl(0x0011, 0).unwrap_err().code(),
+        Some(error::CLI_INVALID_DATA_SIZE_RECVD)
+    );
+
+    // the data length exceeds the response
+    let mut response = szl_response(1, true, &header);
+    response.truncate(38);
+    let mut cl = MockTransport::client(480, vec![response]);
+    assert!(cl.read_szl(0x0011, 0).is_err());
+
+    // a following fragment failed
+    let mut failed = szl_response(2, true, &[]);
+    failed[27..29].copy_from_slice(&[0xD4, 0x01]);
+    let mut cl = MockTransport::client(480, vec![szl_response(1, false, &header), failed]);
+    assert!(matches!(
+        cl.read_szl(0x0011, 0),
+        Err(Error::CPU { code: 0xD401 })
+    ));
+
+    let mut invalid = szl_response(1, true, &header);
+    invalid[29] = 0x0A;
+    let mut cl = MockTransport::client(480, vec![invalid]);
+    assert_eq!(
+        cl.read_szl(0x0011, 0).unwrap_err().code(),
+        Some(error::CLI_INVALID_PLC_ANSWER)
+    );
+}
+
+#[test]
+fn test_communication_capabilities() {
+    let mut general = vec![
+        0x01, 0x31, 0x00, 0x01, 0x00, 40, 0x00, 1, 0x00, 0x01, 0x00, 0xF0,
+    ];
+    general.resize(48, 0);
+    let mut rejected = szl_response(2, true, &[]);
+    rejected[27..29].copy_from_slice(&[0xD4, 0x02]);
+    let mut responses = vec![szl_response(1, true, &general)];
+    responses.resize(8, rejected);
+    let mut cl = MockTransport::client(480, responses);
+    let capabilities = cl.communication_capabilities().unwrap();
+    assert_eq!(capabilities.len(), 1);
+    assert!(matches!(
+        capabilities[0],
+        CommunicationCapabilities::General {
+            max_pdu_length: 240,
+            ..
+        }
+    ));
+    assert_eq!(cl.transport.requests.len(), 8);
+    assert_eq!(cl.transport.requests[7][31..33], [0, 8]);
+}
+
+#[test]
+fn test_module_identification_record_and_leds() {
+    let mut firmware = vec![0x01, 0x11, 0x00, 0x07, 0x00, 28, 0x00, 1, 0x00, 0x07];
+    firmware.extend_from_slice(b"6ES7 315-2EH14-0AB0 ");
+    firmware.extend_from_slice(&[0x00, 0x00, b'V', 3, 0x02, 0x11]);
+    let leds = [
+        0x00, 0x74, 0x00, 0x00, 0x00, 4, 0x00, 2, 0x00, 0x04, 1, 0, 0x00, 0x01, 0, 2,
+    ];
+    let mut cl = MockTransport::client(
+        480,
+        vec![
+            szl_response(1, true, &firmware),
+            szl_response(2, true, &leds),
+            szl_response(3, true, &[0x01, 0x11, 0x00, 0x01, 0x00, 28, 0x00, 0]),
+        ],
+    );
+
+    let record = cl
+        .module_identification_record(ModuleIdentification::INDEX_FIRMWARE)
+        .unwrap();
+    assert_eq!(record.index, ModuleIdentification::INDEX_FIRMWARE);
+    assert_eq!(record.order_number, "6ES7 315-2EH14-0AB0");
+    assert_eq!(record.version(), "V3.2.17");
+    assert_eq!(cl.transport.requests[0][29..33], [0x01, 0x11, 0x00, 0x07]);
+
+    let leds = cl.module_leds().unwrap();
+    assert_eq!(leds.len(), 2);
+    assert_eq!(leds[0].name(), Some("RUN"));
+    assert!(leds[0].on);
+    assert_eq!(leds[1].flash, szl::LedFlash::Slow);
+    assert_eq!(cl.transport.requests[1][29..33], [0x00, 0x74, 0x00, 0x00]);
+
+    assert!(cl.module_identification_record(0x0001).is_err());
+}
+
+#[test]
+fn test_identity() {
+    let component = |index: u16, text: &[u8]| {
+        let mut record = index.to_be_bytes().to_vec();
+        record.extend_from_slice(text);
+        record.resize(34, 0);
+        record
+    };
+    let mut szl_1c = vec![0x00, 0x1C, 0x00, 0x00, 0x00, 34, 0x00, 4];
+    szl_1c.extend(component(1, b"SIMATIC 300(1)"));
+    szl_1c.extend(component(2, b"CPU 315-2 PN/DP"));
+    szl_1c.extend(component(5, b"S C-X4U421302009"));
+    szl_1c.extend(component(7, b"CPU 315-2 PN/DP"));
+
+    let module = |index: u16, version: [u8; 4]| {
+        let mut record = index.to_be_bytes().to_vec();
+        record.extend_from_slice(b"6ES7 315-2EH14-0AB0 \0\xC0");
+        record.extend_from_slice(&version);
+        record
+    };
+    let mut szl_11 = vec![0x00, 0x11, 0x00, 0x00, 0x00, 28, 0x00, 3];
+    szl_11.extend(module(1, [0, 0, 0, 4]));
+    szl_11.extend(module(6, [0, 0, 0, 4]));
+    szl_11.extend(module(7, [b'V', 3, 2, 6]));
+
+    let mut szl_131 = vec![
+        0x01, 0x31, 0x00, 0x01, 0x00, 40, 0x00, 1, 0x00, 0x01, 0x00, 0xF0, 0x00, 16,
+    ];
+    szl_131.extend_from_slice(&187_500u32.to_be_bytes());
+    szl_131.extend_from_slice(&12_000_000u32.to_be_bytes());
+    szl_131.resize(48, 0);
+
+    let mut cl = MockTransport::client(
+        480,
+        vec![
+            szl_response(1, true, &szl_1c),
+            szl_response(2, true, &szl_11),
+            szl_response(3, true, &szl_131),
+        ],
+    );
+    let identity = cl.identity().unwrap();
+    assert_eq!(identity.cpu.as_name, "SIMATIC 300(1)");
+    assert_eq!(identity.cpu.serial_number, "S C-X4U421302009");
+    assert_eq!(identity.cpu.order_number, "6ES7 315-2EH14-0AB0");
+    assert_eq!(identity.cpu.firmware_version.as_deref(), Some("V3.2.6"));
+    assert_eq!(identity.cp.max_pdu_length, 240);
+    assert_eq!(identity.cp.max_bus_rate, 12_000_000);
+    assert_eq!(cl.transport.requests[2][29..33], [0x01, 0x31, 0x00, 0x01]);
+
+    let report = identity.to_string();
+    assert!(report.contains("Serial number: S C-X4U421302009\n"));
+    assert!(report.contains("Hardware version: 4\n"));
+    assert!(report.ends_with("Max bus rate: 12000000 bit/s"));
+    let json = serde_json::to_string(&identity).unwrap();
+    assert_eq!(serde_json::from_str::<Identity>(&json).unwrap(), identity);
+}
+
+/// response to a read (1) or set (2) clock request, userdata group 7 time functions
+#[cfg(test)]
+fn clock_response(subfunction: u8, error: u16, data: &[u8]) -> Vec<u8> {
+    // TPKT, COTP
+    let mut response = vec![3, 0, 0, 0, 2, 240, 128];
+    // userdata header, parameter and data length (15)
+    response.extend_from_slice(&[50, 7, 0, 0, 0, 1, 0, 12, 0, 0]);
+    // parameter head, response of the time functions
+    response.extend_from_slice(&[0, 1, 18, 8, 18, 0x87, subfunction]);
+    // sequence, data unit reference, last data unit, error code (27)
+    response.extend_from_slice(&[0, 0, 0, 0, 0]);
+    // return code, octet string, data length (31)
+    response.extend_from_slice(&[0xFF, 9, 0, 0]);
+    BigEndian::write_u16(response[27..].as_mut(), error);
+    if error != 0 {
+        response[29..31].copy_from_slice(&[0x0A, 0]);
+    }
+    BigEndian::write_u16(response[15..].as_mut(), 4 + data.len() as u16);
+    BigEndian::write_u16(response[31..].as_mut(), data.len() as u16);
+    response.extend_from_slice(data);
+    let len = response.len() as u16;
+    BigEndian::write_u16(response[2..].as_mut(), len);
+    response
+}
+
+#[test]
+fn test_plc_datetime() {
+    // reserved, century, then the BCD year, month, day, hour, minute, second,
+    // milliseconds and weekday of 2024-10-01 12:05:30.250
+    let clock = clock_response(
+        1,
+        0,
+        &[0x00, 0x20, 0x24, 0x10, 0x01, 0x12, 0x05, 0x30, 0x25, 0x03],
+    );
+    let mut cl = MockTransport::client(480, vec![clock, clock_response(2, 0, &[])]);
+    let time = cl.get_plc_datetime().unwrap();
+    assert_eq!(
+        time,
+        DateTime::new(2024, 10, 1, 12, 5, 30, 250_000_000).unwrap()
+    );
+    assert_eq!(cl.transport.requests[0][22..24], [0x47, 0x01]);
+
+    let time = DateTime::new(1999, 12, 31, 23, 59, 58, 7_000_000).unwrap();
+    cl.set_plc_datetime(&time).unwrap();
+    assert_eq!(cl.transport.requests[1][22..24], [0x47, 0x02]);
+    assert_eq!(
+        cl.transport.requests[1][30..],
+        [0x19, 0x99, 0x12, 0x31, 0x23, 0x59, 0x58, 0x00, 0x76]
+    );
+
+    // the time written is read back with the century of the request
+    let mut written = vec![0x00];
+    written.extend_from_slice(&cl.transport.requests[1][30..]);
+    // 1985-06-15 00:00:00.000, a saturday
+    let century = clock_response(
+        1,
+        0,
+        &[0x00, 0x19, 0x85, 0x06, 0x15, 0x00, 0x00, 0x00, 0x00, 0x07],
+    );
+    let mut cl = MockTransport::client(480, vec![clock_response(1, 0, &written), century]);
+    assert_eq!(cl.get_plc_datetime().unwrap(), time);
+    assert_eq!(cl.get_plc_datetime().unwrap().year, 1985);
+
+    let mut cl = MockTransport::client(
+        480,
+        vec![
+            clock_response(1, 0xD602, &[]),
+            clock_response(2, 0xD602, &[]),
+        ],
+    );
+    assert!(matches!(
+        cl.get_plc_datetime(),
+        Err(Error::CPU { code: 0xD602 })
+    ));
+    assert!(cl.set_plc_datetime(&time).is_err());
+    let old = DateTime::new(1980, 1, 1, 0, 0, 0, 0).unwrap();
+    assert!(cl.set_plc_datetime(&old).is_err());
+}
+
+/// response to a set (1) or clear (2) session password request, userdata group 5 security
+#[cfg(test)]
+fn password_response(subfunction: u8, error: u16) -> Vec<u8> {
+    // TPKT, COTP
+    let mut response = vec![3, 0, 0, 33, 2, 240, 128];
+    // userdata header, parameter and data length
+    response.extend_from_slice(&[50, 7, 0, 0, 0, 1, 0, 12, 0, 4]);
+    // parameter head, response of the security functions
+    response.extend_from_slice(&[0, 1, 18, 8, 18, 0x85, subfunction]);
+    // sequence, data unit reference, last data unit, error code (27)
+    response.extend_from_slice(&[0, 0, 0, 0, 0]);
+    // return code, octet string, no data
+    response.extend_from_slice(&[0xFF, 9, 0, 0]);
+    BigEndian::write_u16(response[27..].as_mut(), error);
+    if error != 0 {
+        response[29..31].copy_from_slice(&[0x0A, 0]);
+    }
+    response
+}
+
+#[test]
+fn test_session_password() {
+    assert_eq!(
+        encode_password("abc").unwrap(),
+        [0x34, 0x37, 0x02, 0x42, 0x77, 0x37, 0x02, 0x42]
+    );
+    assert!(encode_password("").is_err());
+    assert!(encode_password("123456789").is_err());
+
+    let mut cl = MockTransport::client(
+        480,
+        vec![
+            password_response(1, 0),
+            password_response(1, 0xD602),
+            password_response(2, 0xD604),
+            password_response(1, 0xD605),
+            password_response(2, 0),
+        ],
+    );
+    cl.set_session_password("secret").unwrap();
+    assert_eq!(cl.transport.requests[0][22..24], [0x45, 0x01]);
+    assert_eq!(
+        cl.transport.requests[0][29..],
+        encode_password("secret").unwrap()
+    );
+
+    let err = cl.set_session_password("wrong").unwrap_err();
+    assert!(matches!(err, Error::InvalidPassword));
+    assert_eq!(err.to_string(), "CPU : Invalid password");
+    assert_eq!(err.code(), Some(0x01E00000));
+    assert!(matches!(
+        cl.clear_session_password(),
+        Err(Error::NoPasswordToSetOrClear)
+    ));
+    assert!(matches!(
+        cl.set_session_password("secret"),
+        Err(Error::NoPasswordToSetOrClear)
+    ));
+    cl.clear_session_password().unwrap();
+    assert_eq!(cl.transport.requests[4][22..24], [0x45, 0x02]);
+}
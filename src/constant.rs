@@ -12,12 +12,27 @@ pub enum Area {
     /// Merkers are address registers within the CPU.
     /// The number of available flag bytes depends on the respective CPU and can be taken from the technical data.
     /// You can use flag bits, flag bytes, flag words or flag double words in a PLC program.
-    // Merker,
+    Merker(DataSizeType),
     /// German thing, means building blocks
     /// This is your storage  : db number, DataSizeType
     DataBausteine(u16, DataSizeType),
-    V(DataSizeType), // Counter,
-                     // Timer,
+    V(DataSizeType),
+    /// Peripheral inputs (PI), bypass the process image, read only
+    PeripheralInput(DataSizeType),
+    /// Peripheral outputs (PQ), bypass the process image, write only
+    PeripheralOutput(DataSizeType),
+    /// Instance data blocks (DI) : db number, DataSizeType
+    InstanceData(u16, DataSizeType),
+    /// Local data (L) of the running block, read only
+    LocalData(DataSizeType),
+    /// Local data of the calling block (previous local data), read only
+    PreviousLocalData(DataSizeType),
+    /// S7 counters, only `DataSizeType::Counter` is accepted.
+    /// The address is the number of the first counter, values are BCD encoded.
+    Counter(DataSizeType),
+    /// S7 timers, only `DataSizeType::Timer` is accepted.
+    /// The address is the number of the first timer, values are S5TIME encoded.
+    Timer(DataSizeType),
 }
 impl Area {
     pub fn area_data(&self) -> u8 {
@@ -25,17 +40,89 @@ impl Area {
             Area::ProcessInput(_) => 0x81,
             Area::ProcessOutput(_) => 0x82,
             Area::V(_) => 0x84,
-            // Area::Merker => {0x83}
-            Area::DataBausteine(_, _) => 0x84, // Area::Counter => {0x1C}
-                                               // Area::Timer => {0x1D}
+            Area::Merker(_) => 0x83,
+            Area::DataBausteine(_, _) => 0x84,
+            Area::PeripheralInput(_) | Area::PeripheralOutput(_) => 0x80,
+            Area::InstanceData(_, _) => 0x85,
+            Area::LocalData(_) => 0x86,
+            Area::PreviousLocalData(_) => 0x87,
+            Area::Counter(_) => 0x1C,
+            Area::Timer(_) => 0x1D,
         }
     }
     pub fn db_number(&self) -> u16 {
         match self {
             Area::ProcessInput(_) => 0,
             Area::ProcessOutput(_) => 0,
+            Area::Merker(_) => 0,
             Area::V(_) => 1,
             Area::DataBausteine(db_number, _) => *db_number,
+            Area::PeripheralInput(_) => 0,
+            Area::PeripheralOutput(_) => 0,
+            Area::InstanceData(db_number, _) => *db_number,
+            Area::LocalData(_) => 0,
+            Area::PreviousLocalData(_) => 0,
+            Area::Counter(_) => 0,
+            Area::Timer(_) => 0,
+        }
+    }
+    /// the same area and db number with another data type
+    pub fn with_data(&self, data: DataSizeType) -> Area {
+        match self {
+            Area::ProcessInput(_) => Area::ProcessInput(data),
+            Area::ProcessOutput(_) => Area::ProcessOutput(data),
+            Area::Merker(_) => Area::Merker(data),
+            Area::DataBausteine(db_number, _) => Area::DataBausteine(*db_number, data),
+            Area::V(_) => Area::V(data),
+            Area::PeripheralInput(_) => Area::PeripheralInput(data),
+            Area::PeripheralOutput(_) => Area::PeripheralOutput(data),
+            Area::InstanceData(db_number, _) => Area::InstanceData(*db_number, data),
+            Area::LocalData(_) => Area::LocalData(data),
+            Area::PreviousLocalData(_) => Area::PreviousLocalData(data),
+            Area::Counter(_) => Area::Counter(data),
+            Area::Timer(_) => Area::Timer(data),
+        }
+    }
+    /// checks that the data type can be used with the area before anything is sent
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        let valid = match self {
+            Area::Counter(val) => matches!(val, DataSizeType::Counter { .. }),
+            Area::Timer(val) => matches!(val, DataSizeType::Timer { .. }),
+            _ => !matches!(
+                **self,
+                DataSizeType::Counter { .. } | DataSizeType::Timer { .. }
+            ),
+        };
+        if !valid {
+            return Err(Error::InvalidInput {
+                input: format!("{:?}: data type not supported by the area", self),
+            });
+        }
+        // the last byte has to be addressable as well
+        self.addr_at(self.byte_len().saturating_sub(1))?;
+        Ok(())
+    }
+    /// checks that the area can be read before anything is sent
+    pub(crate) fn check_read(&self) -> Result<(), Error> {
+        self.check()?;
+        match self {
+            Area::PeripheralOutput(_) => Err(Error::InvalidInput {
+                input: format!("{:?}: peripheral outputs are write only", self),
+            }),
+            _ => Ok(()),
+        }
+    }
+    /// checks that the area can be written before anything is sent
+    pub(crate) fn check_write(&self) -> Result<(), Error> {
+        self.check()?;
+        match self {
+            Area::LocalData(_) | Area::PreviousLocalData(_) => Err(Error::InvalidInput {
+                input: format!("{:?}: local data is read only", self),
+            }),
+            Area::PeripheralInput(_) => Err(Error::InvalidInput {
+                input: format!("{:?}: peripheral inputs are read only", self),
+            }),
+            _ => Ok(()),
         }
     }
 }
@@ -46,12 +133,20 @@ impl Deref for Area {
         match self {
             Area::ProcessInput(val) => val,
             Area::ProcessOutput(val) => val,
+            Area::Merker(val) => val,
             Area::V(val) => val,
             Area::DataBausteine(_, val) => val,
+            Area::PeripheralInput(val) => val,
+            Area::PeripheralOutput(val) => val,
+            Area::InstanceData(_, val) => val,
+            Area::LocalData(val) => val,
+            Area::PreviousLocalData(val) => val,
+            Area::Counter(val) => val,
+            Area::Timer(val) => val,
         }
     }
 }
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 #[serde()]
 pub enum BitAddr {
@@ -84,16 +179,16 @@ impl TryFrom<u16> for BitAddr {
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum DataSizeType {
-    Bit { addr: u16, bit_addr: BitAddr },
-    Byte { addr: u16, len: u16 },
-    Char { addr: u16, len: u16 },
-    Word { addr: u16, len: u16 },
-    Int { addr: u16, len: u16 },
-    DWord { addr: u16, len: u16 },
-    DInt { addr: u16, len: u16 },
-    Real { addr: u16, len: u16 },
-    Counter { addr: u16, len: u16 },
-    Timer { addr: u16, len: u16 },
+    Bit { addr: u32, bit_addr: BitAddr },
+    Byte { addr: u32, len: u32 },
+    Char { addr: u32, len: u32 },
+    Word { addr: u32, len: u32 },
+    Int { addr: u32, len: u32 },
+    DWord { addr: u32, len: u32 },
+    DInt { addr: u32, len: u32 },
+    Real { addr: u32, len: u32 },
+    Counter { addr: u32, len: u32 },
+    Timer { addr: u32, len: u32 },
 }
 impl DataSizeType {
     /// 类型对应的字节长度
@@ -114,10 +209,10 @@ impl DataSizeType {
         }
     }
     /// 读取的单位长度
-    pub fn len(&self) -> u16 {
+    pub fn len(&self) -> u32 {
         use DataSizeType::*;
         match self {
-            Bit { .. } => 1u16,
+            Bit { .. } => 1u32,
             Byte { len, .. } => *len,
             Char { len, .. } => *len,
             Word { len, .. } => *len,
@@ -129,13 +224,17 @@ impl DataSizeType {
             Timer { len, .. } => *len,
         }
     }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     /// 用于返回后的byte长度 = 读取长度 * 单位字节数
     pub fn byte_len(&self) -> usize {
-        (self.len() * self.length()) as usize
+        self.len() as usize * self.length() as usize
     }
-    pub fn addr(&self) -> [u8; 3] {
+    /// byte address, or number of the first element for counters and timers
+    pub fn start(&self) -> u32 {
         use DataSizeType::*;
-        let byte_addr = match self {
+        match self {
             Bit { addr, .. } => *addr,
             Byte { addr, .. } => *addr,
             Char { addr, .. } => *addr,
@@ -146,13 +245,35 @@ impl DataSizeType {
             Real { addr, .. } => *addr,
             Counter { addr, .. } => *addr,
             Timer { addr, .. } => *addr,
+        }
+    }
+    pub fn addr(&self) -> Result<[u8; 3], Error> {
+        self.addr_at(0)
+    }
+    /// address of the data `offset` bytes after the start
+    ///
+    /// Counters and timers are addressed by element number, everything else by bit address.
+    /// The address field is 24 bits wide, which limits byte addresses to `MAX_BYTE_ADDR`.
+    pub fn addr_at(&self, offset: usize) -> Result<[u8; 3], Error> {
+        use DataSizeType::*;
+        let address = match self {
+            Counter { .. } | Timer { .. } => self.start() as u64 + (offset as u64 >> 1),
+            _ => {
+                let byte_addr = self.start() as u64 + offset as u64;
+                if byte_addr > MAX_BYTE_ADDR as u64 {
+                    return Err(Error::InvalidAddr(byte_addr));
+                }
+                (byte_addr << 3) + self.bit_addr() as u64
+            }
         };
-        let address = ((byte_addr as u32) << 3) + self.bit_addr() as u32;
-        [
+        if address > 0x00FFFFFF {
+            return Err(Error::InvalidAddr(address));
+        }
+        Ok([
             ((address & 0x00FF0000) >> 16) as u8,
             ((address & 0x0000FF00) >> 8) as u8,
             (address & 0x000000FF) as u8,
-        ]
+        ])
     }
     pub fn data(&self) -> u8 {
         use DataSizeType::*;
@@ -169,6 +290,15 @@ impl DataSizeType {
             Timer { .. } => 0x1D,
         }
     }
+    /// transport size of the data section in a write request (and read response)
+    pub fn return_transport_size(&self) -> u8 {
+        use DataSizeType::*;
+        match self {
+            Bit { .. } => TS_RES_BIT as u8,
+            Counter { .. } | Timer { .. } => TS_RES_OCTET as u8,
+            _ => TS_RES_BYTE as u8,
+        }
+    }
 }
 
 // Word Length
@@ -193,6 +323,9 @@ impl DataSizeType {
 //     }
 // }
 
+/// transport size used in the item spec when an area is transferred as plain bytes
+pub(crate) const WL_BYTE: u8 = 0x02;
+
 // PLC Status
 pub enum CpuStatus {
     Unknown = 0,
@@ -211,6 +344,12 @@ impl CpuStatus {
     }
 }
 
+/// highest byte address which fits into the 24 bit (bit) address field of a request
+pub const MAX_BYTE_ADDR: u32 = 0x00FFFFFF >> 3;
+
+/// max number of items in a single multi read/write job
+pub const MAX_VARS: usize = 20;
+
 //size header
 pub const SIZE_HEADER_READ: i32 = 31; // Header Size when Reading
 pub const SIZE_HEADER_WRITE: i32 = 35; // Header Size when Writing
@@ -220,7 +359,25 @@ pub const TS_RES_BIT: i32 = 3;
 pub const TS_RES_BYTE: i32 = 4;
 #[allow(dead_code)]
 pub const TS_RES_INT: i32 = 5;
-//todo implement read write multi
-#[allow(dead_code)]
 pub const TS_RES_REAL: i32 = 7;
 pub const TS_RES_OCTET: i32 = 9;
+
+#[test]
+fn test_area_serde() {
+    let areas = vec![
+        Area::Merker(DataSizeType::Bit {
+            addr: 10,
+            bit_addr: BitAddr::Addr3,
+        }),
+        Area::DataBausteine(1, DataSizeType::Real { addr: 4, len: 2 }),
+    ];
+    let json = serde_json::to_string(&areas).unwrap();
+    assert_eq!(
+        json,
+        r#"[{"Merker":{"Bit":{"addr":10,"bit_addr":"Addr3"}}},{"DataBausteine":[1,{"Real":{"addr":4,"len":2}}]}]"#
+    );
+    let areas: Vec<Area> = serde_json::from_str(&json).unwrap();
+    assert_eq!(areas[0].area_data(), 0x83);
+    assert_eq!(areas[0].db_number(), 0);
+    assert_eq!(areas[0].bit_addr(), 3);
+}
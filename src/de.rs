@@ -0,0 +1,359 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Deserializes values from the memory layout of standard (non-optimized) data blocks
+//!
+//! The layout rules are the same as for `ser`. The format is not self describing,
+//! so only types with a fixed layout can be deserialized: no `Vec`, options, maps or enums.
+//!
+//! # Examples
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Motor {
+//!     running: bool,
+//!     fault: bool,
+//!     speed: i16,
+//! }
+//!
+//! let motor: Motor = s7::de::from_bytes(&[0b01, 0, 0x01, 0x2C]).unwrap();
+//! assert!(motor.running);
+//! assert!(!motor.fault);
+//! assert_eq!(motor.speed, 300);
+//! ```
+
+use crate::error::Error;
+use crate::ser::string_from_bytes;
+use byteorder::{BigEndian, ByteOrder};
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use serde::Deserialize;
+
+/// deserializes a `T` from the bytes of a data block, all bytes have to be used
+pub fn from_bytes<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, Error> {
+    let mut deserializer = Deserializer::from_bytes(bytes);
+    let value = T::deserialize(&mut deserializer)?;
+    if deserializer.pos != bytes.len() {
+        return Err(Error::Serde(format!(
+            "{} trailing bytes",
+            bytes.len() - deserializer.pos
+        )));
+    }
+    Ok(value)
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::Serde(format!("{} has no S7 representation", what))
+}
+
+/// reads values in the S7 data block layout
+#[derive(Debug)]
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+    pos: usize,
+    /// next bit of the last byte to read a bool from, 0 starts a new byte
+    bit: u8,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn from_bytes(input: &'de [u8]) -> Self {
+        Deserializer {
+            input,
+            pos: 0,
+            bit: 0,
+        }
+    }
+
+    fn align_byte(&mut self) {
+        self.bit = 0;
+    }
+
+    /// the padding after the last value may be missing
+    fn align_word(&mut self) {
+        self.align_byte();
+        if self.pos % 2 == 1 && self.pos < self.input.len() {
+            self.pos += 1;
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'de [u8], Error> {
+        if self.pos + len > self.input.len() {
+            return Err(Error::Serde(format!(
+                "expected {} more bytes at offset {} got {}",
+                len,
+                self.pos,
+                self.input.len() - self.pos
+            )));
+        }
+        let bytes = &self.input[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        self.align_byte();
+        Ok(self.take(1)?[0])
+    }
+
+    fn word(&mut self, len: usize) -> Result<&'de [u8], Error> {
+        self.align_word();
+        self.take(len)
+    }
+
+    fn compound<V: Visitor<'de>>(&mut self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.align_word();
+        let value = visitor.visit_seq(Compound {
+            de: self,
+            remaining: len,
+        })?;
+        self.align_word();
+        Ok(value)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(unsupported("a value of unknown type"))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.bit == 0 {
+            self.take(1)?;
+        }
+        let value = self.input[self.pos - 1] & (1 << self.bit) != 0;
+        self.bit = (self.bit + 1) % 8;
+        visitor.visit_bool(value)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i8(self.byte()? as i8)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i16(BigEndian::read_i16(self.word(2)?))
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i32(BigEndian::read_i32(self.word(4)?))
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i64(BigEndian::read_i64(self.word(8)?))
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u8(self.byte()?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u16(BigEndian::read_u16(self.word(2)?))
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u32(BigEndian::read_u32(self.word(4)?))
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u64(BigEndian::read_u64(self.word(8)?))
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f32(BigEndian::read_f32(self.word(4)?))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f64(BigEndian::read_f64(self.word(8)?))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_char(self.byte()? as char)
+    }
+
+    /// the length of the STRING is taken from its header
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let max_len = self.word(2)?[0] as usize;
+        self.pos -= 2;
+        let bytes = self.take(max_len + 2)?;
+        visitor.visit_string(string_from_bytes(bytes)?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(unsupported("a byte buffer without a fixed length"))
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(unsupported("a byte buffer without a fixed length"))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(unsupported("Option"))
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(unsupported(
+            "a sequence without a fixed length, use an array",
+        ))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.compound(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.compound(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(unsupported("map"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.compound(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(unsupported(name))
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(unsupported("identifier"))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(unsupported("a value of unknown type"))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// members of a struct, tuple or array
+struct Compound<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    remaining: usize,
+}
+
+impl<'de> SeqAccess<'de> for Compound<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+#[test]
+fn test_deserialize() {
+    use crate::ser::{to_bytes, FixedString};
+    use serde::Serialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Axis {
+        enabled: bool,
+        position: f32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Machine {
+        running: bool,
+        fault: bool,
+        mode: i8,
+        speed: i16,
+        flags: [bool; 10],
+        axes: [Axis; 2],
+        name: FixedString<3>,
+        comment: String,
+        total: u64,
+    }
+
+    let machine = Machine {
+        running: false,
+        fault: true,
+        mode: -1,
+        speed: 300,
+        flags: [
+            false, true, false, false, false, false, false, false, true, true,
+        ],
+        axes: [
+            Axis {
+                enabled: true,
+                position: 1.5,
+            },
+            Axis {
+                enabled: false,
+                position: -2.0,
+            },
+        ],
+        name: FixedString("abc".to_string()),
+        comment: "hello".to_string(),
+        total: u64::MAX,
+    };
+    let bytes = to_bytes(&machine).unwrap();
+    assert_eq!(bytes.len(), 2 + 2 + 2 + 12 + 6 + 256 + 8);
+    assert_eq!(from_bytes::<Machine>(&bytes).unwrap(), machine);
+
+    // every member of an S7 struct is read from its own offset
+    let motor: (bool, bool, i16) = from_bytes(&[0b10, 0, 0x01, 0x2C]).unwrap();
+    assert_eq!(motor, (false, true, 300));
+
+    assert!(from_bytes::<Machine>(&bytes[..bytes.len() - 1]).is_err());
+    assert!(from_bytes::<(bool, i16)>(&[0, 0, 0, 0, 0, 0]).is_err());
+    assert!(from_bytes::<Vec<u8>>(&[0, 0]).is_err());
+    assert!(from_bytes::<FixedString<2>>(&[3, 2, b'a', b'b', 0, 0]).is_err());
+    // the actual length exceeds the max length
+    assert!(from_bytes::<String>(&[2, 3, b'a', b'b']).is_err());
+}
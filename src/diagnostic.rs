@@ -0,0 +1,236 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Entries of the CPU diagnostic buffer, SZL 0x00A0
+//!
+//! The CPU returns the newest entry first. Every entry is 20 bytes: event ID, priority class,
+//! OB number, data ID, two additional infos and the BCD encoded time of the event.
+
+use crate::error::Error;
+use crate::szl::Szl;
+use crate::value::DateTime;
+use byteorder::{BigEndian, ByteOrder};
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::fmt;
+
+/// SZL-ID of the diagnostic buffer, index 0 returns all entries
+pub const SZL_ID_DIAGNOSTIC_BUFFER: u16 = 0x00A0;
+
+const ENTRY_SIZE: usize = 20;
+
+/// event class, the highest 4 bits of the event ID
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventClass {
+    StandardOb,
+    SynchronousError,
+    AsynchronousError,
+    ModeTransition,
+    RunTime,
+    Communication,
+    FaultTolerant,
+    ModuleDiagnostic,
+    User,
+    Unknown(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticEntry {
+    pub event_id: u16,
+    pub priority: u8,
+    pub ob_number: u8,
+    pub data_id: u16,
+    pub info_1: u16,
+    pub info_2: u32,
+    /// `None` when the time is not a valid DATE_AND_TIME
+    pub time: Option<DateTime>,
+}
+
+impl DiagnosticEntry {
+    /// decodes the entries of SZL 0x00A0 or 0x01A0, newest first
+    pub fn decode(szl: &Szl) -> Result<Vec<DiagnosticEntry>, Error> {
+        if (szl.header.length_header as usize) < ENTRY_SIZE {
+            return Err(Error::InvalidResponse {
+                reason: format!(
+                    "diagnostic buffer entries of {} bytes",
+                    szl.header.length_header
+                ),
+                bytes: szl.data.clone(),
+            });
+        }
+        Ok(szl
+            .records()
+            .map(|record| DiagnosticEntry {
+                event_id: BigEndian::read_u16(&record[0..]),
+                priority: record[2],
+                ob_number: record[3],
+                data_id: BigEndian::read_u16(&record[4..]),
+                info_1: BigEndian::read_u16(&record[6..]),
+                info_2: BigEndian::read_u32(&record[8..]),
+                time: DateTime::from_bcd_bytes(record[12..20].try_into().unwrap()).ok(),
+            })
+            .collect())
+    }
+
+    /// the entries of `entries` before `seen`, all entries when `seen` is no longer buffered
+    pub fn newer_than<'a>(
+        entries: &'a [DiagnosticEntry],
+        seen: &DiagnosticEntry,
+    ) -> &'a [DiagnosticEntry] {
+        let end = entries
+            .iter()
+            .position(|entry| entry == seen)
+            .unwrap_or(entries.len());
+        &entries[..end]
+    }
+
+    pub fn event_class(&self) -> EventClass {
+        match self.event_id >> 12 {
+            0x1 => EventClass::StandardOb,
+            0x2 => EventClass::SynchronousError,
+            0x3 => EventClass::AsynchronousError,
+            0x4 => EventClass::ModeTransition,
+            0x5 => EventClass::RunTime,
+            0x6 => EventClass::Communication,
+            0x7 => EventClass::FaultTolerant,
+            0x8 => EventClass::ModuleDiagnostic,
+            0x9..=0xB => EventClass::User,
+            other => EventClass::Unknown(other as u8),
+        }
+    }
+
+    /// the event is entering its state, ie a module fault which appears
+    pub fn incoming(&self) -> bool {
+        self.event_id & 0x0100 != 0
+    }
+
+    /// text of the well known events, the event class for all others
+    pub fn description(&self) -> String {
+        let text = match self.event_id {
+            0x4301 => "Mode transition from STOP to STARTUP",
+            0x4302 => "Mode transition from STARTUP to RUN",
+            0x4303 => "STOP caused by stop switch being activated",
+            0x4304 => "STOP caused by PG STOP operation or by SFB 20 STOP",
+            0x4305 => "HOLD: breakpoint reached",
+            0x4306 => "HOLD: breakpoint exited",
+            0x4307 => "Memory reset started by PG operation",
+            0x4308 => "Memory reset started by switch setting",
+            0x4309 => "Memory reset started automatically (power on not backed up)",
+            0x430A => "HOLD exited, transition to STOP",
+            0x4520 => "DEFECT: STOP not possible",
+            0x4562 => "STOP caused by programming error (OB not loaded or not possible)",
+            0x4563 => "STOP caused by I/O access error (OB not loaded or not possible)",
+            0x4568 => "STOP caused by time error (OB not loaded or not possible)",
+            0x456A => "STOP caused by diagnostic interrupt (OB not loaded or not possible)",
+            0x456B => "STOP caused by removing/inserting module (OB not loaded or not possible)",
+            0x456C => "STOP caused by CPU hardware error (OB not loaded or not possible)",
+            0x3501 => "Cycle time exceeded",
+            0x3502 => "User interface (OB or FRB) request error",
+            0x3503 => "Delay too long processing a priority class",
+            0x3505 => "Time-of-day interrupt(s) skipped due to new clock setting",
+            0x3507 => "Multiple OB request errors caused internal buffer overflow",
+            0x35A1 => "OB start failed: user interface (OB or FRB) not found",
+            0x35A2 => "OB start failed: OB not loaded",
+            0x35A3 => "Error when operating system accesses a block",
+            0x3861 => "Module/interface module inserted, module type OK",
+            0x3863 => "Module/interface module inserted, but wrong module type",
+            0x3961 => "Module/interface module removed, cannot be addressed",
+            _ => {
+                let class = match self.event_class() {
+                    EventClass::StandardOb => "Standard OB event",
+                    EventClass::SynchronousError => "Synchronous error",
+                    EventClass::AsynchronousError => "Asynchronous error",
+                    EventClass::ModeTransition => "Mode transition",
+                    EventClass::RunTime => "Run-time event",
+                    EventClass::Communication => "Communication event",
+                    EventClass::FaultTolerant => "H/F system event",
+                    EventClass::ModuleDiagnostic if self.incoming() => "Module fault",
+                    EventClass::ModuleDiagnostic => "Module fault removed",
+                    EventClass::User => "User event",
+                    EventClass::Unknown(_) => "Unknown event",
+                };
+                return format!("{} {:#06X}", class, self.event_id);
+            }
+        };
+        text.to_string()
+    }
+}
+
+impl fmt::Display for DiagnosticEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.time {
+            Some(time) => write!(
+                f,
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03} ",
+                time.year,
+                time.month,
+                time.day,
+                time.hour,
+                time.minute,
+                time.second,
+                time.nanosecond / 1_000_000
+            )?,
+            None => write!(f, "{:23} ", "")?,
+        }
+        write!(
+            f,
+            "{:#06X} OB{} {}",
+            self.event_id,
+            self.ob_number,
+            self.description()
+        )
+    }
+}
+
+#[test]
+fn test_diagnostic_entries() {
+    use crate::szl::SzlHeader;
+
+    let records: [[u8; 20]; 3] = [
+        [
+            0x35, 0x01, 0x1A, 80, 0, 0, 0x01, 0x2C, 0, 0, 0, 0x96, 0x24, 0x10, 0x01, 0x12, 0x05,
+            0x30, 0x25, 0x05,
+        ],
+        [
+            0x43, 0x02, 0xFF, 100, 0xFF, 0x84, 0, 0, 0, 0, 0, 0, 0x24, 0x10, 0x01, 0x12, 0x00,
+            0x00, 0x00, 0x03,
+        ],
+        [
+            0x89, 0x42, 0x1A, 82, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ],
+    ];
+    let szl = Szl {
+        header: SzlHeader {
+            id: SZL_ID_DIAGNOSTIC_BUFFER,
+            index: 0,
+            length_header: 20,
+            number_of_data_record: 3,
+        },
+        data: records.concat(),
+    };
+    let entries = DiagnosticEntry::decode(&szl).unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].event_class(), EventClass::AsynchronousError);
+    assert_eq!(entries[0].ob_number, 80);
+    assert_eq!(entries[0].info_1, 300);
+    assert_eq!(
+        entries[0].to_string(),
+        "2024-10-01 12:05:30.250 0x3501 OB80 Cycle time exceeded"
+    );
+    assert_eq!(
+        entries[1].description(),
+        "Mode transition from STARTUP to RUN"
+    );
+    assert_eq!(entries[2].time, None);
+    assert!(entries[2].incoming());
+    assert_eq!(entries[2].description(), "Module fault 0x8942");
+
+    assert_eq!(DiagnosticEntry::newer_than(&entries, &entries[1]).len(), 1);
+    assert_eq!(DiagnosticEntry::newer_than(&entries, &entries[0]).len(), 0);
+    let gone = DiagnosticEntry {
+        event_id: 0x4301,
+        ..entries[1].clone()
+    };
+    assert_eq!(DiagnosticEntry::newer_than(&entries, &gone).len(), 3);
+}
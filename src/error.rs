@@ -26,10 +26,10 @@ const CLI_INVALID_PARAMS: i32 = 0x00200000;
 const CLI_JOB_PENDING: i32 = 0x00300000;
 const CLI_TOO_MANY_ITEMS: i32 = 0x00400000;
 const CLI_INVALID_DWORD_LEN: i32 = 0x00500000;
-const CLI_PARTIAL_DATA_WRITTEN: i32 = 0x00600000;
+pub(crate) const CLI_PARTIAL_DATA_WRITTEN: i32 = 0x00600000;
 const CLI_SIZE_OVER_PDU: i32 = 0x00700000;
 pub(crate) const CLI_INVALID_PLC_ANSWER: i32 = 0x00800000;
-const CLI_ADDRESS_OUT_OF_RANGE: i32 = 0x00900000;
+pub(crate) const CLI_ADDRESS_OUT_OF_RANGE: i32 = 0x00900000;
 const CLI_INVALID_TRANSPORT_SIZE: i32 = 0x00A00000;
 const CLI_WRITE_DATA_SIZE_MISMATCH: i32 = 0x00B00000;
 const CLI_ITEM_NOT_AVAILABLE: i32 = 0x00C00000;
@@ -42,7 +42,7 @@ const CLI_CANNOT_COMPRESS: i32 = 0x01200000;
 pub(crate) const CLI_ALREADY_STOP: i32 = 0x01300000;
 const CLI_FUN_NOT_AVAILABLE: i32 = 0x01400000;
 const CLI_UPLOAD_SEQUENCE_FAILED: i32 = 0x01500000;
-const CLI_INVALID_DATA_SIZE_RECVD: i32 = 0x01600000;
+pub(crate) const CLI_INVALID_DATA_SIZE_RECVD: i32 = 0x01600000;
 const CLI_INVALID_BLOCK_TYPE: i32 = 0x01700000;
 const CLI_INVALID_BLOCK_NUMBER: i32 = 0x01800000;
 const CLI_INVALID_BLOCK_SIZE: i32 = 0x01900000;
@@ -50,7 +50,7 @@ const CLI_NEED_PASSWORD: i32 = 0x01D00000;
 const CLI_INVALID_PASSWORD: i32 = 0x01E00000;
 const CLI_NO_PASSWORD_TO_SET_OR_CLEAR: i32 = 0x01F00000;
 const CLI_JOB_TIMEOUT: i32 = 0x02000000;
-const CLI_PARTIAL_DATA_READ: i32 = 0x02100000;
+pub(crate) const CLI_PARTIAL_DATA_READ: i32 = 0x02100000;
 const CLI_BUFFER_TOO_SMALL: i32 = 0x02200000;
 const CLI_FUNCTION_REFUSED: i32 = 0x02300000;
 const CLI_DESTROYING: i32 = 0x02400000;
@@ -71,21 +71,41 @@ const CODE_7_NO_PASSWORD_TO_SET: i32 = 54789;
 const CODE_7_FUN_NOT_AVAILABLE: i32 = 33028;
 const CODE_7_DATA_OVER_PDU: i32 = 34048;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Error {
     Connect(String),
     Lock,
     IOError(ErrorKind),
-    Response { code: i32 },
-    CPU { code: i32 },
-    InvalidInput { input: String },
+    Response {
+        code: i32,
+    },
+    CPU {
+        code: i32,
+    },
+    InvalidInput {
+        input: String,
+    },
     Send,
     Iso,
     PduLength(u16),
     TryFrom(Vec<u8>, String),
     InvalidCpuStatus(u8),
-    InvalidResponse { reason: String, bytes: Vec<u8> },
+    InvalidResponse {
+        reason: String,
+        bytes: Vec<u8>,
+    },
     InvalidBitAddr(u16),
+    InvalidAddr(u64),
+    Serde(String),
+    /// the CPU rejected the session password
+    InvalidPassword,
+    /// the CPU is not protected or no password is set
+    NoPasswordToSetOrClear,
+    /// some items of `Client::write_multi` were written and others failed, the result of
+    /// every item in the order of the request
+    PartialWrite {
+        results: Vec<Result<(), Error>>,
+    },
 }
 
 impl fmt::Display for Error {
@@ -112,10 +132,60 @@ impl fmt::Display for Error {
             Error::InvalidBitAddr(addr) => {
                 write!(f, "Invalid bit addr {}", addr)
             }
+            Error::InvalidAddr(addr) => {
+                write!(f, "Invalid addr {} exceeds the 24 bit address field", addr)
+            }
+            Error::Serde(reason) => write!(f, "S7 layout error: {}", reason),
+            Error::InvalidPassword => write!(f, "{}", error_text(CLI_INVALID_PASSWORD)),
+            Error::NoPasswordToSetOrClear => {
+                write!(f, "{}", error_text(CLI_NO_PASSWORD_TO_SET_OR_CLEAR))
+            }
+            Error::PartialWrite { results } => write!(
+                f,
+                "{}, {} of {} items failed",
+                error_text(CLI_PARTIAL_DATA_WRITTEN),
+                results.iter().filter(|result| result.is_err()).count(),
+                results.len()
+            ),
+        }
+    }
+}
+
+impl Error {
+    /// error code of a response error, CPU errors are mapped to the client error codes
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            Error::Response { code } => Some(*code),
+            Error::CPU { code } => Some(cpu_error(*code)),
+            Error::InvalidPassword => Some(CLI_INVALID_PASSWORD),
+            Error::NoPasswordToSetOrClear => Some(CLI_NO_PASSWORD_TO_SET_OR_CLEAR),
+            Error::PartialWrite { .. } => Some(CLI_PARTIAL_DATA_WRITTEN),
+            _ => None,
+        }
+    }
+
+    /// error of a CPU response to a password function, password errors get their own variant
+    pub(crate) fn password(code: i32) -> Error {
+        match cpu_error(code) {
+            CLI_INVALID_PASSWORD => Error::InvalidPassword,
+            CLI_NO_PASSWORD_TO_SET_OR_CLEAR => Error::NoPasswordToSetOrClear,
+            _ => Error::CPU { code },
         }
     }
 }
 
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Serde(msg.to_string())
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Serde(msg.to_string())
+    }
+}
+
 impl From<IOError> for Error {
     fn from(e: IOError) -> Self {
         Error::IOError(e.kind())
@@ -2,10 +2,14 @@
 // This software may be modified and distributed under the terms
 // of the BSD license. See the LICENSE file for details.
 
-//! Parses bytes from `Area::DataBausteine` to types for easier manipulation
+//! Parses bytes from PLC memory to types for easier manipulation
 
+use super::constant::{Area, BitAddr, DataSizeType};
 use super::error::Error;
+use super::value::{self, DateTime};
 use byteorder::{BigEndian, ByteOrder};
+use std::any::Any;
+use std::time::Duration;
 
 /// Fields collection type alias for convenience
 /// # Examples
@@ -13,16 +17,17 @@ use byteorder::{BigEndian, ByteOrder};
 /// ```
 /// use s7::field::{Float, Bool, Fields};
 ///
-/// let float = Float::new(888, 8.0, vec![66, 86, 0, 0]).unwrap();
-/// let boolean = Bool::new(888, 8.0, vec![1u8]).unwrap();
+/// let float = Float::new("DB888.DBD8".parse().unwrap(), vec![66, 86, 0, 0]).unwrap();
+/// let boolean = Bool::new("DB888.DBX12.0".parse().unwrap(), vec![1u8]).unwrap();
 /// println!("bool: {}", boolean.value());
 /// println!("float: {}", float.value());
 /// let fields: Fields = vec![Box::new(float), Box::new(boolean)];
 ///
 /// for field in fields.iter() {
 ///     println!(
-///         "saving bytes {:?} to block {} offset {}",
+///         "saving bytes {:?} to {} (block {} offset {})",
 ///         field.to_bytes(),
+///         field.area(),
 ///         field.data_block(),
 ///         field.offset()
 ///     )
@@ -32,53 +37,106 @@ pub type Fields = Vec<Box<dyn Field>>;
 
 /// represents a type stored in the hardware
 /// ie `bool`, `real(32 bit float)`
-pub trait Field {
-    /// data block
-    fn data_block(&self) -> i32;
-    /// offset in the data block
-    /// for convenience, we truncate the float
-    /// we don't care about the digits after the decimal point anymore
-    fn offset(&self) -> i32;
+pub trait Field: Any {
+    /// exact address of the field, it can be passed to `Client::read` and `Client::write` as is
+    fn area(&self) -> Area;
+    /// data block number, 0 for areas without data blocks
+    fn data_block(&self) -> u16 {
+        self.area().db_number()
+    }
+    /// byte offset in the area
+    fn offset(&self) -> u32 {
+        self.area().start()
+    }
+    /// bit position in the byte, only set for `Bool`
+    fn bit(&self) -> Option<BitAddr> {
+        match *self.area() {
+            DataSizeType::Bit { bit_addr, .. } => Some(bit_addr),
+            _ => None,
+        }
+    }
 
     fn to_bytes(&self) -> Vec<u8>;
+
+    /// replaces the value with bytes read from the PLC, the same bytes `new` expects
+    fn load(&mut self, bytes: &[u8]) -> Result<(), Error>;
+    /// whether the value changed since the field was created or loaded
+    fn is_dirty(&self) -> bool;
+}
+
+impl dyn Field {
+    /// the concrete field, to access the value of a field in `Fields`
+    pub fn downcast_ref<F: Field>(&self) -> Option<&F> {
+        (self as &dyn Any).downcast_ref()
+    }
+
+    /// the concrete field, to change the value of a field in `Fields`
+    pub fn downcast_mut<F: Field>(&mut self) -> Option<&mut F> {
+        (self as &mut dyn Any).downcast_mut()
+    }
+}
+
+/// places a field with the type `data` at the area, db number and start address of `area`
+/// and checks the buffer size. Every type but `Bool` starts at a whole byte.
+fn field_area(name: &str, area: Area, data: DataSizeType, bytes: &[u8]) -> Result<Area, Error> {
+    if let DataSizeType::Bit { bit_addr, .. } = *area {
+        return Err(Error::TryFrom(
+            bytes.to_vec(),
+            format!(
+                "{}.new: {} should not have a bit offset got {}",
+                name, name, bit_addr as u8
+            ),
+        ));
+    }
+
+    let area = area.with_data(data);
+    if let Err(e) = area.check() {
+        return Err(Error::TryFrom(
+            bytes.to_vec(),
+            format!("{}.new: {}", name, e),
+        ));
+    }
+
+    if bytes.len() != area.byte_len() {
+        return Err(Error::TryFrom(
+            bytes.to_vec(),
+            format!(
+                "{}.new: expected buf size {} got {}",
+                name,
+                area.byte_len(),
+                bytes.len()
+            ),
+        ));
+    }
+    Ok(area)
 }
 
 /// PLC float field
 #[derive(Debug)]
 pub struct Float {
-    data_block: i32,
-    /// offset example 8.1
-    /// left side is index within the block
-    /// right side is the bit position only used for bool, zero for all other types
-    offset: f32,
+    area: Area,
     value: f32,
+    /// the bytes the field was created or loaded from
+    loaded: Vec<u8>,
 }
 
 impl Float {
-    pub fn new(data_block: i32, offset: f32, mut bytes: Vec<u8>) -> Result<Float, Error> {
-        let len = bytes.len();
-        if bytes.len() != Float::size() as usize {
-            return Err(Error::TryFrom(
-                bytes,
-                format!("Float.new: expected buf size {} got {}", Float::size(), len),
-            ));
-        }
-
-        let bit_offset = ((offset * 10.0) as usize % 10) as u8;
-        if bit_offset != 0 {
-            return Err(Error::TryFrom(
-                bytes,
-                format!(
-                    "Float.new: float should not have a bit offset got {}",
-                    bit_offset
-                ),
-            ));
-        }
+    /// only the area, data block and byte offset of `area` are used
+    pub fn new(area: Area, bytes: Vec<u8>) -> Result<Float, Error> {
+        let area = field_area(
+            "Float",
+            area,
+            DataSizeType::Real {
+                addr: area.start(),
+                len: 1,
+            },
+            &bytes,
+        )?;
 
         Ok(Float {
-            data_block,
-            offset,
-            value: BigEndian::read_f32(bytes.as_mut_slice()),
+            area,
+            value: BigEndian::read_f32(&bytes),
+            loaded: bytes,
         })
     }
 
@@ -96,38 +154,42 @@ impl Float {
 }
 
 impl Field for Float {
-    fn data_block(&self) -> i32 {
-        self.data_block
-    }
-
-    fn offset(&self) -> i32 {
-        self.offset as i32
+    fn area(&self) -> Area {
+        self.area
     }
 
     fn to_bytes(&self) -> Vec<u8> {
         let mut buf = vec![0u8; Float::size() as usize];
         BigEndian::write_f32(buf.as_mut_slice(), self.value);
-        return buf;
+        buf
+    }
+
+    fn load(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        *self = Float::new(self.area, bytes.to_vec())?;
+        Ok(())
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.to_bytes() != self.loaded
     }
 }
 
-/// Bool represents a single bit in a byte from `Area::DataBausteine`
+/// Bool represents a single bit in a byte
 #[derive(Debug)]
 pub struct Bool {
-    /// index of the block it's stored at
-    data_block: i32,
-    /// example 8.1
-    /// left side is index within the block
-    /// right side is the bit position only used for bool, zero for all other types
-    offset: f32,
+    /// the bit address of the value
+    area: Area,
     /// the actual primitive value
     byte: u8,
     /// the current value that will be written to the byte
     value: bool,
+    /// the value the field was created or loaded with
+    loaded: bool,
 }
 
 impl Bool {
-    pub fn new(data_block: i32, offset: f32, bytes: Vec<u8>) -> Result<Self, Error> {
+    /// `area` has to be a `DataSizeType::Bit` address, `bytes` is the byte containing it
+    pub fn new(area: Area, bytes: Vec<u8>) -> Result<Self, Error> {
         let len = bytes.len();
         if bytes.len() != Self::size() as usize {
             return Err(Error::TryFrom(
@@ -136,19 +198,22 @@ impl Bool {
             ));
         }
 
-        let bit_offset = ((offset * 10.0) as usize % 10) as u8;
-        if bit_offset > 7 {
+        if !matches!(*area, DataSizeType::Bit { .. }) {
             return Err(Error::TryFrom(
                 bytes,
-                format!("Bool.new: max offset is 7 got {}", offset),
+                format!("Bool.new: expected a bit address got {:?}", area),
             ));
         }
+        if let Err(e) = area.check() {
+            return Err(Error::TryFrom(bytes, format!("Bool.new: {}", e)));
+        }
 
+        let value = bytes[0] & (1 << area.bit_addr()) != 0;
         Ok(Bool {
-            data_block,
-            offset,
+            area,
             byte: bytes[0],
-            value: bytes[0] & (1 << bit_offset) != 0,
+            value,
+            loaded: value,
         })
     }
 
@@ -157,7 +222,7 @@ impl Bool {
         if val {
             return b | (1 << bit_pos);
         }
-        return b & !(1 << bit_pos);
+        b & !(1 << bit_pos)
     }
 
     pub fn size() -> i32 {
@@ -171,32 +236,530 @@ impl Bool {
 
     pub fn set_value(&mut self, v: bool) {
         self.value = v;
-        self.byte = Bool::set_value_at(
-            self.byte,
-            ((self.offset * 10.0) as usize % 10) as u8,
-            self.value,
-        );
+        self.byte = Bool::set_value_at(self.byte, self.area.bit_addr(), self.value);
     }
 }
 
 impl Field for Bool {
-    fn data_block(&self) -> i32 {
-        self.data_block
+    fn area(&self) -> Area {
+        self.area
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![self.byte]
+    }
+
+    fn load(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        *self = Bool::new(self.area, bytes.to_vec())?;
+        Ok(())
+    }
+
+    /// only the bit itself counts, the other bits of the byte are never written
+    fn is_dirty(&self) -> bool {
+        self.value != self.loaded
+    }
+}
+
+/// defines a fixed size field stored as `len` elements of the `data` type,
+/// `decode` parses the PLC bytes and `encode` converts the value back.
+/// Fields with an infallible `encode` have a plain `set_value`, the others validate the value.
+macro_rules! fixed_field {
+    ($(#[$doc:meta])* $name:ident($value:ty, $data:ident * $len:expr), fallible $decode:expr, fallible $encode:expr) => {
+        $(#[$doc])*
+        #[derive(Debug)]
+        pub struct $name {
+            area: Area,
+            value: $value,
+            bytes: Vec<u8>,
+            /// the bytes the field was created or loaded from
+            loaded: Vec<u8>,
+        }
+
+        impl $name {
+            /// only the area, data block and byte offset of `area` are used
+            pub fn new(area: Area, bytes: Vec<u8>) -> Result<$name, Error> {
+                let data = DataSizeType::$data {
+                    addr: area.start(),
+                    len: $len,
+                };
+                let area = field_area(stringify!($name), area, data, &bytes)?;
+                let decode: fn(&[u8]) -> Result<$value, Error> = $decode;
+                Ok($name {
+                    area,
+                    value: decode(&bytes)?,
+                    loaded: bytes.clone(),
+                    bytes,
+                })
+            }
+
+            pub fn size() -> i32 {
+                DataSizeType::$data { addr: 0, len: $len }.byte_len() as i32
+            }
+
+            pub fn value(&self) -> $value {
+                self.value
+            }
+
+            /// fails if the value can't be represented by the PLC type
+            pub fn set_value(&mut self, v: $value) -> Result<(), Error> {
+                let encode: fn(&$value) -> Result<Vec<u8>, Error> = $encode;
+                self.bytes = encode(&v)?;
+                self.value = v;
+                Ok(())
+            }
+        }
+
+        impl Field for $name {
+            fn area(&self) -> Area {
+                self.area
+            }
+
+            fn to_bytes(&self) -> Vec<u8> {
+                self.bytes.clone()
+            }
+
+            fn load(&mut self, bytes: &[u8]) -> Result<(), Error> {
+                *self = $name::new(self.area, bytes.to_vec())?;
+                Ok(())
+            }
+
+            fn is_dirty(&self) -> bool {
+                self.bytes != self.loaded
+            }
+        }
+    };
+    ($(#[$doc:meta])* $name:ident($value:ty, $data:ident * $len:expr), $decode:expr, $encode:expr) => {
+        $(#[$doc])*
+        #[derive(Debug)]
+        pub struct $name {
+            area: Area,
+            value: $value,
+            /// the bytes the field was created or loaded from
+            loaded: Vec<u8>,
+        }
+
+        impl $name {
+            /// only the area, data block and byte offset of `area` are used
+            pub fn new(area: Area, bytes: Vec<u8>) -> Result<$name, Error> {
+                let data = DataSizeType::$data {
+                    addr: area.start(),
+                    len: $len,
+                };
+                let area = field_area(stringify!($name), area, data, &bytes)?;
+                let decode: fn(&[u8]) -> $value = $decode;
+                Ok($name {
+                    area,
+                    value: decode(&bytes),
+                    loaded: bytes,
+                })
+            }
+
+            pub fn size() -> i32 {
+                DataSizeType::$data { addr: 0, len: $len }.byte_len() as i32
+            }
+
+            pub fn value(&self) -> $value {
+                self.value
+            }
+
+            pub fn set_value(&mut self, v: $value) {
+                self.value = v
+            }
+        }
+
+        impl Field for $name {
+            fn area(&self) -> Area {
+                self.area
+            }
+
+            fn to_bytes(&self) -> Vec<u8> {
+                let encode: fn(&$value) -> Vec<u8> = $encode;
+                encode(&self.value)
+            }
+
+            fn load(&mut self, bytes: &[u8]) -> Result<(), Error> {
+                *self = $name::new(self.area, bytes.to_vec())?;
+                Ok(())
+            }
+
+            fn is_dirty(&self) -> bool {
+                self.to_bytes() != self.loaded
+            }
+        }
+    };
+}
+
+fixed_field!(
+    /// PLC BYTE field
+    Byte(u8, Byte * 1),
+    |b| b[0],
+    |v| vec![*v]
+);
+
+fixed_field!(
+    /// PLC SINT field
+    SInt(i8, Byte * 1),
+    |b| b[0] as i8,
+    |v| vec![*v as u8]
+);
+
+fixed_field!(
+    /// PLC WORD field
+    Word(u16, Word * 1),
+    BigEndian::read_u16,
+    |v| v.to_be_bytes().to_vec()
+);
+
+fixed_field!(
+    /// PLC INT field
+    Int(i16, Int * 1),
+    BigEndian::read_i16,
+    |v| v.to_be_bytes().to_vec()
+);
+
+fixed_field!(
+    /// PLC DWORD field
+    DWord(u32, DWord * 1),
+    BigEndian::read_u32,
+    |v| v.to_be_bytes().to_vec()
+);
+
+fixed_field!(
+    /// PLC DINT field
+    DInt(i32, DInt * 1),
+    BigEndian::read_i32,
+    |v| v.to_be_bytes().to_vec()
+);
+
+fixed_field!(
+    /// PLC LREAL field (64 bit float)
+    LReal(f64, Byte * 8),
+    BigEndian::read_f64,
+    |v| v.to_be_bytes().to_vec()
+);
+
+fixed_field!(
+    /// PLC LINT field
+    LInt(i64, Byte * 8),
+    BigEndian::read_i64,
+    |v| v.to_be_bytes().to_vec()
+);
+
+fixed_field!(
+    /// PLC ULINT field
+    ULInt(u64, Byte * 8),
+    BigEndian::read_u64,
+    |v| v.to_be_bytes().to_vec()
+);
+
+fixed_field!(
+    /// PLC TIME field, signed duration in milliseconds
+    Time(i32, DInt * 1),
+    BigEndian::read_i32,
+    |v| v.to_be_bytes().to_vec()
+);
+
+fixed_field!(
+    /// PLC CHAR field, a single latin-1 character
+    Char(char, Char * 1),
+    fallible |b| Ok(b[0] as char),
+    fallible |v| {
+        if (*v as u32) > 0xFF {
+            return Err(Error::InvalidInput {
+                input: format!("Char: {} is not a latin-1 character", v),
+            });
+        }
+        Ok(vec![*v as u8])
+    }
+);
+
+fixed_field!(
+    /// PLC S5TIME field
+    S5Time(Duration, Word * 1),
+    fallible |b| value::s5time_from_bytes([b[0], b[1]]),
+    fallible |v| Ok(value::s5time_to_bytes(*v)?.to_vec())
+);
+
+fixed_field!(
+    /// PLC DATE field, the time of day of the value is always zero
+    Date(DateTime, Word * 1),
+    fallible |b| Ok(DateTime::from_date_bytes([b[0], b[1]])),
+    fallible |v| Ok(v.to_date_bytes()?.to_vec())
+);
+
+fixed_field!(
+    /// PLC TIME_OF_DAY field, milliseconds since midnight
+    TimeOfDay(Duration, DWord * 1),
+    fallible |b| {
+        let millis = BigEndian::read_u32(b);
+        if millis >= MILLIS_PER_DAY {
+            return Err(Error::TryFrom(
+                b.to_vec(),
+                format!("TimeOfDay.new: {}ms exceeds a day", millis),
+            ));
+        }
+        Ok(Duration::from_millis(millis as u64))
+    },
+    fallible |v| {
+        if v.as_millis() >= MILLIS_PER_DAY as u128 {
+            return Err(Error::InvalidInput {
+                input: format!("TimeOfDay: {:?} exceeds a day", v),
+            });
+        }
+        Ok((v.as_millis() as u32).to_be_bytes().to_vec())
+    }
+);
+
+fixed_field!(
+    /// PLC DATE_AND_TIME field, BCD encoded with millisecond resolution
+    DateAndTime(DateTime, Byte * 8),
+    fallible |b| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(b);
+        DateTime::from_bcd_bytes(bytes)
+    },
+    fallible |v| Ok(v.to_bcd_bytes()?.to_vec())
+);
+
+fixed_field!(
+    /// PLC DTL field, date and time with nanosecond resolution
+    Dtl(DateTime, Byte * 12),
+    fallible |b| {
+        let mut bytes = [0u8; 12];
+        bytes.copy_from_slice(b);
+        DateTime::from_dtl_bytes(bytes)
+    },
+    fallible |v| Ok(v.to_dtl_bytes().to_vec())
+);
+
+const MILLIS_PER_DAY: u32 = 86_400_000;
+
+/// PLC STRING field
+/// the first byte is the max length, the second the actual length followed by the latin-1 characters
+#[derive(Debug)]
+pub struct S7String {
+    area: Area,
+    max_len: u8,
+    value: String,
+    /// the value the field was created or loaded with
+    loaded: String,
+}
+
+impl S7String {
+    /// only the area, data block and byte offset of `area` are used
+    pub fn new(area: Area, bytes: Vec<u8>) -> Result<S7String, Error> {
+        if bytes.len() < 2 {
+            return Err(Error::TryFrom(
+                bytes,
+                "S7String.new: missing length header".to_string(),
+            ));
+        }
+        let max_len = bytes[0];
+        let data = DataSizeType::Byte {
+            addr: area.start(),
+            len: S7String::size(max_len) as u32,
+        };
+        let area = field_area("S7String", area, data, &bytes)?;
+        let len = bytes[1];
+        if len > max_len {
+            return Err(Error::TryFrom(
+                bytes,
+                format!(
+                    "S7String.new: actual length {} exceeds max length {}",
+                    len, max_len
+                ),
+            ));
+        }
+
+        let value: String = bytes[2..2 + len as usize]
+            .iter()
+            .map(|b| *b as char)
+            .collect();
+        Ok(S7String {
+            area,
+            max_len,
+            loaded: value.clone(),
+            value,
+        })
     }
 
-    fn offset(&self) -> i32 {
-        self.offset as i32
+    /// size of a string with `max_len` characters including the header
+    pub fn size(max_len: u8) -> i32 {
+        max_len as i32 + 2
+    }
+
+    pub fn max_len(&self) -> u8 {
+        self.max_len
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// fails if the value exceeds the max length or contains non latin-1 characters
+    pub fn set_value(&mut self, v: &str) -> Result<(), Error> {
+        if v.chars().count() > self.max_len as usize || v.chars().any(|c| c as u32 > 0xFF) {
+            return Err(Error::InvalidInput {
+                input: format!("S7String: {:?} does not fit a STRING[{}]", v, self.max_len),
+            });
+        }
+        self.value = v.to_string();
+        Ok(())
+    }
+}
+
+impl Field for S7String {
+    fn area(&self) -> Area {
+        self.area
     }
 
     fn to_bytes(&self) -> Vec<u8> {
-        vec![self.byte]
+        let mut buf = vec![0u8; S7String::size(self.max_len) as usize];
+        buf[0] = self.max_len;
+        buf[1] = self.value.chars().count() as u8;
+        for (i, c) in self.value.chars().enumerate() {
+            buf[2 + i] = c as u8;
+        }
+        buf
+    }
+    fn load(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        *self = S7String::new(self.area, bytes.to_vec())?;
+        Ok(())
+    }
+
+    /// the bytes after the actual length are ignored
+    fn is_dirty(&self) -> bool {
+        self.value != self.loaded
+    }
+}
+
+/// PLC WSTRING field
+/// the first word is the max length, the second the actual length followed by the UTF-16 characters
+#[derive(Debug)]
+pub struct WString {
+    area: Area,
+    max_len: u16,
+    value: String,
+    /// the value the field was created or loaded with
+    loaded: String,
+}
+
+impl WString {
+    /// only the area, data block and byte offset of `area` are used
+    pub fn new(area: Area, bytes: Vec<u8>) -> Result<WString, Error> {
+        if bytes.len() < 4 {
+            return Err(Error::TryFrom(
+                bytes,
+                "WString.new: missing length header".to_string(),
+            ));
+        }
+        let max_len = BigEndian::read_u16(&bytes[0..]);
+        let data = DataSizeType::Byte {
+            addr: area.start(),
+            len: WString::size(max_len) as u32,
+        };
+        let area = field_area("WString", area, data, &bytes)?;
+        let len = BigEndian::read_u16(&bytes[2..]);
+        if len > max_len {
+            return Err(Error::TryFrom(
+                bytes,
+                format!(
+                    "WString.new: actual length {} exceeds max length {}",
+                    len, max_len
+                ),
+            ));
+        }
+
+        let units: Vec<u16> = bytes[4..4 + len as usize * 2]
+            .chunks(2)
+            .map(BigEndian::read_u16)
+            .collect();
+        let value = match String::from_utf16(&units) {
+            Ok(value) => value,
+            Err(e) => return Err(Error::TryFrom(bytes, format!("WString.new: {}", e))),
+        };
+
+        Ok(WString {
+            area,
+            max_len,
+            loaded: value.clone(),
+            value,
+        })
+    }
+
+    /// size of a string with `max_len` characters including the header
+    pub fn size(max_len: u16) -> i32 {
+        max_len as i32 * 2 + 4
+    }
+
+    pub fn max_len(&self) -> u16 {
+        self.max_len
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// fails if the value exceeds the max length
+    pub fn set_value(&mut self, v: &str) -> Result<(), Error> {
+        if v.encode_utf16().count() > self.max_len as usize {
+            return Err(Error::InvalidInput {
+                input: format!("WString: {:?} does not fit a WSTRING[{}]", v, self.max_len),
+            });
+        }
+        self.value = v.to_string();
+        Ok(())
     }
 }
 
+impl Field for WString {
+    fn area(&self) -> Area {
+        self.area
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0u8; WString::size(self.max_len) as usize];
+        BigEndian::write_u16(&mut buf[0..], self.max_len);
+        let mut len = 0;
+        for (i, unit) in self.value.encode_utf16().enumerate() {
+            BigEndian::write_u16(&mut buf[4 + i * 2..], unit);
+            len += 1;
+        }
+        BigEndian::write_u16(&mut buf[2..], len);
+        buf
+    }
+    fn load(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        *self = WString::new(self.area, bytes.to_vec())?;
+        Ok(())
+    }
+
+    /// the bytes after the actual length are ignored
+    fn is_dirty(&self) -> bool {
+        self.value != self.loaded
+    }
+}
+
+#[cfg(test)]
+fn db_byte(addr: u32) -> Area {
+    Area::DataBausteine(888, DataSizeType::Byte { addr, len: 1 })
+}
+
+#[cfg(test)]
+fn db_bit(addr: u32, bit: u16) -> Area {
+    use std::convert::TryFrom;
+    Area::DataBausteine(
+        888,
+        DataSizeType::Bit {
+            addr,
+            bit_addr: BitAddr::try_from(bit).unwrap(),
+        },
+    )
+}
+
 #[test]
 fn test_fields() {
-    let float = Float::new(888, 8.0, vec![66, 86, 0, 0]).unwrap();
-    let boolean = Bool::new(888, 8.0, vec![1u8]).unwrap();
+    let float = Float::new(db_byte(8), vec![66, 86, 0, 0]).unwrap();
+    let boolean = Bool::new(db_bit(8, 0), vec![1u8]).unwrap();
     assert!(boolean.value());
     assert_eq!(53.5, float.value());
     let fields: Fields = vec![Box::new(float), Box::new(boolean)];
@@ -211,63 +774,204 @@ fn test_fields() {
     }
 }
 
+#[test]
+fn test_field_area() {
+    let float = Float::new(db_byte(8), vec![66, 86, 0, 0]).unwrap();
+    assert_eq!(float.area().to_string(), "P#DB888.DBX8.0 REAL 1");
+    assert!(matches!(
+        *float.area(),
+        DataSizeType::Real { addr: 8, len: 1 }
+    ));
+    assert_eq!(
+        (float.data_block(), float.offset(), float.bit()),
+        (888, 8, None)
+    );
+
+    // offsets are exact, even where a f32 would round
+    let boolean = Bool::new(db_bit(16_777_215 >> 3, 3), vec![0b1000]).unwrap();
+    assert!(boolean.value());
+    assert_eq!(boolean.offset(), 2_097_151);
+    assert_eq!(boolean.bit(), Some(BitAddr::Addr3));
+    assert_eq!(boolean.area().to_string(), "DB888.DBX2097151.3");
+
+    let lreal = LReal::new(
+        Area::Merker(DataSizeType::Word { addr: 4, len: 1 }),
+        vec![0; 8],
+    )
+    .unwrap();
+    assert_eq!(lreal.area().to_string(), "P#M4.0 BYTE 8");
+    assert_eq!(lreal.data_block(), 0);
+
+    let s = S7String::new("DB1.DBB10".parse().unwrap(), vec![2, 0, 0, 0]).unwrap();
+    assert_eq!(s.area().byte_len(), 4);
+
+    assert!(Bool::new(db_byte(8), vec![0]).is_err());
+    assert!(Int::new(
+        Area::Counter(DataSizeType::Counter { addr: 0, len: 1 }),
+        vec![0; 2]
+    )
+    .is_err());
+    assert!(DWord::new(db_byte(0x1FFFFE), vec![0; 4]).is_err());
+}
+
 #[test]
 fn test_float() {
     let val: f32 = 53.5;
     let mut b = vec![0u8; Float::size() as usize];
     BigEndian::write_f32(b.as_mut_slice(), val);
-    let mut field = Float::new(888, 8.0, b).unwrap();
+    let mut field = Float::new(db_byte(8), b).unwrap();
     field.set_value(val);
     let result = field.to_bytes();
 
     assert_eq!(vec![66, 86, 0, 0], result);
 
-    // test invalid bit offset
-    // float should not have a bit offset
-    match Float::new(888, 8.1, vec![66, 86, 0, 0]) {
-        Ok(_) => {
-            println!("should return an error at invalid bit offset 1. Floats should not have a bit offset");
-            assert!(false)
-        }
-        Err(_) => {}
-    }
+    // floats can't have a bit offset
+    assert!(Float::new(db_bit(8, 1), vec![66, 86, 0, 0]).is_err());
 }
 
 #[test]
 fn test_bool() {
+    use std::convert::TryFrom;
     let b = vec![1u8; 1];
-    let mut field = Bool::new(888, 8.1, b).unwrap();
+    let mut field = Bool::new(db_bit(8, 1), b).unwrap();
     field.set_value(true);
 
     let mut res: Vec<u8> = field.to_bytes();
 
     assert_eq!(res.len(), 1);
     assert_eq!(res[0], 3);
-    assert_eq!(field.value(), true);
+    assert!(field.value());
 
     field.set_value(false);
     res = field.to_bytes();
 
     assert_eq!(res.len(), 1);
     assert_eq!(res[0], 1);
-    assert_eq!(field.value(), false);
+    assert!(!field.value());
 
     let bb = vec![0b00001000u8; 1];
-    field = Bool::new(888, 8.4, bb).unwrap();
+    field = Bool::new(db_bit(8, 4), bb).unwrap();
     field.set_value(true);
 
     res = field.to_bytes();
 
     assert_eq!(res.len(), 1);
     assert_eq!(res[0], 24);
-    assert_eq!(field.value(), true);
+    assert!(field.value());
 
-    // test invalid bit offset
-    match Bool::new(888, 8.8, vec![0b00001000u8; 1]) {
-        Ok(_) => {
-            println!("should return an error at invalid bit offset 8");
-            assert!(false)
-        }
-        Err(_) => {}
-    }
+    // bits only go up to 7
+    assert!(BitAddr::try_from(8).is_err());
+}
+
+#[test]
+fn test_numeric_fields() {
+    let mut int = Int::new(db_byte(2), vec![0xFF, 0xFE]).unwrap();
+    assert_eq!(int.value(), -2);
+    int.set_value(300);
+    assert_eq!(int.to_bytes(), vec![0x01, 0x2C]);
+
+    let lreal = LReal::new(db_byte(4), 1.5f64.to_be_bytes().to_vec()).unwrap();
+    assert_eq!(lreal.value(), 1.5);
+    assert_eq!(lreal.to_bytes(), 1.5f64.to_be_bytes().to_vec());
+
+    let ulint = ULInt::new(db_byte(4), vec![0xFF; 8]).unwrap();
+    assert_eq!(ulint.value(), u64::MAX);
+    let lint = LInt::new(db_byte(4), vec![0xFF; 8]).unwrap();
+    assert_eq!(lint.value(), -1);
+    let mut sint = SInt::new(db_byte(1), vec![0x80]).unwrap();
+    assert_eq!(sint.value(), -128);
+    sint.set_value(-1);
+    assert_eq!(sint.to_bytes(), vec![0xFF]);
+
+    assert_eq!(
+        DWord::new(db_byte(0), vec![0, 0, 1, 0]).unwrap().value(),
+        256
+    );
+    assert_eq!(Time::new(db_byte(0), vec![0xFF; 4]).unwrap().value(), -1);
+    assert!(Word::new(db_byte(0), vec![0]).is_err());
+    assert!(DInt::new(db_bit(0, 1), vec![0; 4]).is_err());
+    assert!(Byte::new(db_bit(3, 5), vec![0]).is_err());
+
+    let mut c = Char::new(db_byte(0), vec![b'A']).unwrap();
+    assert_eq!(c.value(), 'A');
+    c.set_value('\u{e4}').unwrap();
+    assert_eq!(c.to_bytes(), vec![0xE4]);
+    assert!(c.set_value('€').is_err());
+    assert_eq!(c.value(), '\u{e4}');
+}
+
+#[test]
+fn test_time_fields() {
+    let mut s5 = S5Time::new(db_byte(0), vec![0x21, 0x27]).unwrap();
+    assert_eq!(s5.value(), Duration::from_secs(127));
+    s5.set_value(Duration::from_millis(500)).unwrap();
+    assert_eq!(s5.to_bytes(), vec![0x00, 0x50]);
+
+    let mut tod = TimeOfDay::new(db_byte(0), 3_600_000u32.to_be_bytes().to_vec()).unwrap();
+    assert_eq!(tod.value(), Duration::from_secs(3600));
+    assert!(tod.set_value(Duration::from_secs(86_400)).is_err());
+    assert!(TimeOfDay::new(db_byte(0), 86_400_000u32.to_be_bytes().to_vec()).is_err());
+
+    let date = Date::new(db_byte(0), vec![0x2D, 0x5D]).unwrap();
+    assert_eq!(
+        (date.value().year, date.value().month, date.value().day),
+        (2021, 10, 18)
+    );
+
+    let bytes = vec![0x23, 0x05, 0x17, 0x13, 0x45, 0x09, 0x12, 0x34];
+    let mut dt = DateAndTime::new(db_byte(0), bytes.clone()).unwrap();
+    assert_eq!(dt.to_bytes(), bytes);
+    let too_late = DateTime::new(2100, 1, 1, 0, 0, 0, 0).unwrap();
+    assert!(dt.set_value(too_late).is_err());
+    assert_eq!(dt.to_bytes(), bytes);
+
+    let mut dtl = Dtl::new(db_byte(0), dt.value().to_dtl_bytes().to_vec()).unwrap();
+    assert_eq!(dtl.value(), dt.value());
+    dtl.set_value(too_late).unwrap();
+    assert_eq!(dtl.to_bytes()[..2], [0x08, 0x34]);
+}
+
+#[test]
+fn test_string_fields() {
+    let mut s = S7String::new(db_byte(0), vec![4, 2, b'h', b'i', 0, 0]).unwrap();
+    assert_eq!(s.value(), "hi");
+    assert_eq!(s.max_len(), 4);
+    s.set_value("abcd").unwrap();
+    assert_eq!(s.to_bytes(), vec![4, 4, b'a', b'b', b'c', b'd']);
+    assert!(s.set_value("abcde").is_err());
+    assert!(S7String::new(db_byte(0), vec![4, 5, 0, 0, 0, 0]).is_err());
+    assert!(S7String::new(db_byte(0), vec![4, 0, 0]).is_err());
+    assert!(S7String::new(db_bit(0, 1), vec![0, 0]).is_err());
+
+    let mut w = WString::new(db_byte(0), vec![0, 2, 0, 1, 0x20, 0xAC, 0, 0]).unwrap();
+    assert_eq!(w.value(), "€");
+    w.set_value("ab").unwrap();
+    assert_eq!(w.to_bytes(), vec![0, 2, 0, 2, 0, b'a', 0, b'b']);
+    assert!(w.set_value("abc").is_err());
+    assert_eq!(WString::size(2), 8);
+}
+
+#[test]
+fn test_dirty() {
+    let mut field = Bool::new(db_bit(8, 1), vec![0b11]).unwrap();
+    assert!(!field.is_dirty());
+    field.set_value(false);
+    assert!(field.is_dirty());
+    field.load(&[0b01]).unwrap();
+    assert!(!field.is_dirty());
+    assert!(!field.value());
+
+    let mut s = S7String::new(db_byte(0), vec![2, 1, b'a', b'x']).unwrap();
+    s.set_value("a").unwrap();
+    assert!(!s.is_dirty());
+    s.set_value("b").unwrap();
+    assert!(s.is_dirty());
+    assert!(s.load(&[3, 0, 0, 0]).is_err());
+
+    let mut word = Word::new(db_byte(2), vec![0, 1]).unwrap();
+    word.set_value(2);
+    assert!(word.is_dirty());
+    word.set_value(1);
+    assert!(!word.is_dirty());
+    assert!(word.load(&[0]).is_err());
 }
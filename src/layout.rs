@@ -0,0 +1,531 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Maps structs to the memory layout of standard (non-optimized) data blocks
+//!
+//! With the `derive` feature `#[derive(S7Layout)]` computes the offsets of the members
+//! following the Step7 rules and converts them with the `field` types.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use s7::S7Layout;
+//!
+//! #[derive(S7Layout)]
+//! #[s7(db = 10)]
+//! struct Recipe {
+//!     active: bool,          // DBX0.0
+//!     heating: bool,         // DBX0.1
+//!     speed: i16,            // DBW2
+//!     #[s7(string_len = 20)]
+//!     name: String,          // DBB4, STRING[20]
+//!     #[s7(offset = "30.3")]
+//!     alarm: bool,           // DBX30.3
+//! }
+//!
+//! let recipe = Recipe::from_bytes(&client.read(Recipe::area())?)?;
+//! ```
+
+use crate::constant::{Area, BitAddr, DataSizeType};
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// a struct stored in a data block
+pub trait S7Layout: Sized {
+    /// size of the struct in the data block in bytes
+    const SIZE: usize;
+
+    /// parses the struct from `SIZE` bytes
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error>;
+
+    /// converts the struct to `SIZE` bytes, fails if a value can't be represented by the PLC type
+    fn to_bytes(&self) -> Result<Vec<u8>, Error>;
+
+    /// area covering the whole struct stored in data block `db` starting at byte `offset`
+    fn area_at(db: u16, offset: u32) -> Area {
+        Area::DataBausteine(
+            db,
+            DataSizeType::Byte {
+                addr: offset,
+                len: Self::SIZE as u32,
+            },
+        )
+    }
+}
+
+/// area of a member relative to the start of the struct, used by the derive macro
+#[doc(hidden)]
+pub fn member_area(byte: u32) -> Area {
+    Area::DataBausteine(0, DataSizeType::Byte { addr: byte, len: 1 })
+}
+
+/// area of a bool member relative to the start of the struct, used by the derive macro
+#[doc(hidden)]
+pub fn member_bit_area(byte: u32, bit: u16) -> Result<Area, Error> {
+    Ok(Area::DataBausteine(
+        0,
+        DataSizeType::Bit {
+            addr: byte,
+            bit_addr: BitAddr::try_from(bit)?,
+        },
+    ))
+}
+
+/// checks the buffer passed to `S7Layout::from_bytes`, used by the derive macro
+#[doc(hidden)]
+pub fn check_size(name: &str, size: usize, bytes: &[u8]) -> Result<(), Error> {
+    if bytes.len() != size {
+        return Err(Error::TryFrom(
+            bytes.to_vec(),
+            format!(
+                "{}.from_bytes: expected buf size {} got {}",
+                name,
+                size,
+                bytes.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// type of a member of a data block, STRUCT or UDT
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DataType {
+    Bool,
+    Byte,
+    Char,
+    SInt,
+    USInt,
+    Word,
+    Int,
+    UInt,
+    DWord,
+    DInt,
+    UDInt,
+    Real,
+    LWord,
+    LInt,
+    ULInt,
+    LReal,
+    S5Time,
+    Time,
+    Date,
+    TimeOfDay,
+    DateAndTime,
+    Dtl,
+    /// STRING[n], n + 2 bytes
+    String(u8),
+    /// WSTRING[n], 2 * n + 4 bytes
+    WString(u16),
+    /// ARRAY[lower..upper, ...] OF element, the bounds of every dimension are inclusive
+    Array {
+        dims: Vec<(i32, i32)>,
+        element: Box<DataType>,
+    },
+    Struct(Vec<Member>),
+    /// user defined type: name and members
+    Udt(String, Vec<Member>),
+}
+
+/// named member of a data block, STRUCT or UDT
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Member {
+    pub name: String,
+    pub data_type: DataType,
+}
+
+impl Member {
+    pub fn new(name: &str, data_type: DataType) -> Member {
+        Member {
+            name: name.to_string(),
+            data_type,
+        }
+    }
+}
+
+/// how a type is aligned in a standard data block
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Align {
+    /// packed into the current byte
+    Bit,
+    /// next whole byte
+    Byte,
+    /// next even byte
+    Word,
+}
+
+impl DataType {
+    fn align(&self) -> Align {
+        use DataType::*;
+        match self {
+            Bool => Align::Bit,
+            Byte | Char | SInt | USInt => Align::Byte,
+            _ => Align::Word,
+        }
+    }
+
+    /// data type to read an elementary type with, `None` for arrays, structs and UDTs
+    fn data_size_type(&self, addr: u32, bit: u8) -> Option<DataSizeType> {
+        use DataType::*;
+        let bytes = |len: u32| Some(DataSizeType::Byte { addr, len });
+        match self {
+            Bool => Some(DataSizeType::Bit {
+                addr,
+                bit_addr: BitAddr::try_from(bit as u16).ok()?,
+            }),
+            Byte | SInt | USInt => bytes(1),
+            Char => Some(DataSizeType::Char { addr, len: 1 }),
+            Word | UInt | S5Time | Date => Some(DataSizeType::Word { addr, len: 1 }),
+            Int => Some(DataSizeType::Int { addr, len: 1 }),
+            DWord | UDInt | TimeOfDay => Some(DataSizeType::DWord { addr, len: 1 }),
+            DInt | Time => Some(DataSizeType::DInt { addr, len: 1 }),
+            Real => Some(DataSizeType::Real { addr, len: 1 }),
+            LWord | LInt | ULInt | LReal | DateAndTime => bytes(8),
+            Dtl => bytes(12),
+            String(len) => bytes(*len as u32 + 2),
+            WString(len) => bytes(*len as u32 * 2 + 4),
+            Array { .. } | Struct(_) | Udt(..) => None,
+        }
+    }
+}
+
+/// a member placed in a data block
+#[derive(Debug, Clone)]
+pub struct LayoutMember {
+    /// path like `Recipe.Speed[3]` or `Matrix[1,2]`
+    pub path: String,
+    /// bytes of arrays, structs and UDTs, the exact type of everything else
+    pub area: Area,
+    pub data_type: DataType,
+}
+
+/// offsets of all members of a standard (non-optimized) data block
+///
+/// Computed like Step7 does: bools are packed into bytes, BYTE, CHAR, SINT and USINT start at
+/// the next whole byte, every other type at the next even byte. Arrays, structs and UDTs start at
+/// an even byte and take an even number of bytes, the bools of an ARRAY OF BOOL are packed.
+#[derive(Debug, Clone)]
+pub struct DbLayout {
+    /// size of the block in bytes
+    pub size: u32,
+    /// every member in declaration order, arrays, structs and UDTs precede their elements
+    pub members: Vec<LayoutMember>,
+}
+
+impl DbLayout {
+    /// lays out the members of data block `db`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use s7::layout::{DataType, DbLayout, Member};
+    ///
+    /// let layout = DbLayout::compute(
+    ///     10,
+    ///     &[
+    ///         Member::new("Active", DataType::Bool),
+    ///         Member::new("Speed", DataType::Int),
+    ///         Member::new(
+    ///             "Temperatures",
+    ///             DataType::Array {
+    ///                 dims: vec![(1, 3)],
+    ///                 element: Box::new(DataType::Real),
+    ///             },
+    ///         ),
+    ///     ],
+    /// )
+    /// .unwrap();
+    /// assert_eq!(layout.size, 16);
+    /// assert_eq!(layout.get("Temperatures[2]").unwrap().area.to_string(), "P#DB10.DBX8.0 REAL 1");
+    /// ```
+    pub fn compute(db: u16, members: &[Member]) -> Result<DbLayout, Error> {
+        let mut cursor = Cursor {
+            db,
+            byte: 0,
+            bit: 0,
+            members: Vec::new(),
+        };
+        for member in members {
+            cursor.place(&member.name, &member.data_type)?;
+        }
+        cursor.align_word();
+        if cursor.byte > crate::constant::MAX_BYTE_ADDR + 1 {
+            return Err(Error::InvalidAddr(cursor.byte as u64));
+        }
+        Ok(DbLayout {
+            size: cursor.byte,
+            members: cursor.members,
+        })
+    }
+
+    /// the member at `path`
+    pub fn get(&self, path: &str) -> Option<&LayoutMember> {
+        self.members.iter().find(|member| member.path == path)
+    }
+
+    /// path and area of every member, to read them individually with `Client::read`
+    pub fn areas(&self) -> Vec<(String, Area)> {
+        self.members
+            .iter()
+            .map(|member| (member.path.clone(), member.area))
+            .collect()
+    }
+}
+
+/// next free position while laying out a block
+struct Cursor {
+    db: u16,
+    byte: u32,
+    bit: u8,
+    members: Vec<LayoutMember>,
+}
+
+impl Cursor {
+    fn align_byte(&mut self) {
+        if self.bit > 0 {
+            self.byte += 1;
+            self.bit = 0;
+        }
+    }
+
+    fn align_word(&mut self) {
+        self.align_byte();
+        self.byte += self.byte % 2;
+    }
+
+    /// bits between two elements of an array of `element`
+    fn element_bits(&self, element: &DataType) -> Result<u64, Error> {
+        if *element == DataType::Bool {
+            return Ok(1);
+        }
+        let mut scratch = Cursor {
+            db: self.db,
+            byte: 0,
+            bit: 0,
+            members: Vec::new(),
+        };
+        scratch.place("", element)?;
+        match element.align() {
+            Align::Word => scratch.align_word(),
+            _ => scratch.align_byte(),
+        }
+        Ok(scratch.byte as u64 * 8)
+    }
+
+    fn place(&mut self, path: &str, data_type: &DataType) -> Result<(), Error> {
+        match data_type.align() {
+            Align::Bit => {}
+            Align::Byte => self.align_byte(),
+            Align::Word => self.align_word(),
+        }
+        let (start, bit) = (self.byte, self.bit);
+
+        if let Some(data) = data_type.data_size_type(start, bit) {
+            match data_type {
+                DataType::String(0) | DataType::String(255) | DataType::WString(0) => {
+                    return Err(Error::InvalidInput {
+                        input: format!("{}: invalid string length {:?}", path, data_type),
+                    })
+                }
+                DataType::Bool => {
+                    self.bit += 1;
+                    if self.bit == 8 {
+                        self.byte += 1;
+                        self.bit = 0;
+                    }
+                }
+                _ => self.byte += data.byte_len() as u32,
+            }
+            self.members.push(LayoutMember {
+                path: path.to_string(),
+                area: Area::DataBausteine(self.db, data),
+                data_type: data_type.clone(),
+            });
+            return Ok(());
+        }
+
+        // the size of arrays, structs and UDTs is known after their elements are placed
+        let index = self.members.len();
+        self.members.push(LayoutMember {
+            path: path.to_string(),
+            area: Area::DataBausteine(
+                self.db,
+                DataSizeType::Byte {
+                    addr: start,
+                    len: 0,
+                },
+            ),
+            data_type: data_type.clone(),
+        });
+        match data_type {
+            DataType::Array { dims, element } => {
+                if dims.is_empty() || dims.iter().any(|(lower, upper)| lower > upper) {
+                    return Err(Error::InvalidInput {
+                        input: format!("{}: invalid array bounds {:?}", path, dims),
+                    });
+                }
+                // checked before the elements are placed, one member is added per element
+                let count = dims
+                    .iter()
+                    .try_fold(1u64, |count, (lower, upper)| {
+                        count.checked_mul((*upper as i64 - *lower as i64 + 1) as u64)
+                    })
+                    .ok_or_else(|| Error::InvalidInput {
+                        input: format!("{}: too many array elements {:?}", path, dims),
+                    })?;
+                let end = count
+                    .checked_mul(self.element_bits(element)?)
+                    .map_or(u64::MAX, |bits| start as u64 + bits.div_ceil(8));
+                if end > crate::constant::MAX_BYTE_ADDR as u64 + 1 {
+                    return Err(Error::InvalidAddr(end));
+                }
+                let mut indexes: Vec<i32> = dims.iter().map(|(lower, _)| *lower).collect();
+                loop {
+                    let index: Vec<String> = indexes.iter().map(|i| i.to_string()).collect();
+                    self.place(&format!("{}[{}]", path, index.join(",")), element)?;
+                    // the last index changes fastest
+                    let mut dim = dims.len();
+                    loop {
+                        if dim == 0 {
+                            break;
+                        }
+                        dim -= 1;
+                        if indexes[dim] < dims[dim].1 {
+                            indexes[dim] += 1;
+                            break;
+                        }
+                        indexes[dim] = dims[dim].0;
+                    }
+                    if indexes.iter().zip(dims).all(|(i, (lower, _))| i == lower) {
+                        break;
+                    }
+                }
+            }
+            DataType::Struct(members) | DataType::Udt(_, members) => {
+                for member in members {
+                    self.place(&format!("{}.{}", path, member.name), &member.data_type)?;
+                }
+            }
+            _ => unreachable!("elementary types have a data size type"),
+        }
+        self.align_word();
+        self.members[index].area = Area::DataBausteine(
+            self.db,
+            DataSizeType::Byte {
+                addr: start,
+                len: self.byte - start,
+            },
+        );
+        Ok(())
+    }
+}
+
+#[test]
+fn test_db_layout() {
+    let motor = DataType::Udt(
+        "Motor".to_string(),
+        vec![
+            Member::new("Running", DataType::Bool),
+            Member::new("Speed", DataType::Real),
+        ],
+    );
+    let members = vec![
+        Member::new("A", DataType::Bool),
+        Member::new("B", DataType::Bool),
+        Member::new("Mode", DataType::Byte),
+        Member::new("Letter", DataType::Char),
+        Member::new("Count", DataType::Int),
+        Member::new("C", DataType::Bool),
+        Member::new(
+            "Flags",
+            DataType::Array {
+                dims: vec![(-1, 8)],
+                element: Box::new(DataType::Bool),
+            },
+        ),
+        Member::new("Name", DataType::String(3)),
+        Member::new("Total", DataType::DInt),
+        Member::new(
+            "Matrix",
+            DataType::Array {
+                dims: vec![(1, 2), (0, 1)],
+                element: Box::new(DataType::Byte),
+            },
+        ),
+        Member::new(
+            "Recipe",
+            DataType::Struct(vec![
+                Member::new("Step", DataType::USInt),
+                Member::new(
+                    "Motors",
+                    DataType::Array {
+                        dims: vec![(1, 2)],
+                        element: Box::new(motor),
+                    },
+                ),
+            ]),
+        ),
+        Member::new("Last", DataType::Byte),
+    ];
+    let layout = DbLayout::compute(5, &members).unwrap();
+
+    let offset = |path: &str| {
+        let area = layout.get(path).unwrap().area;
+        (area.start(), area.bit_addr(), area.byte_len())
+    };
+    assert_eq!(offset("A"), (0, 0, 1));
+    assert_eq!(offset("B"), (0, 1, 1));
+    assert_eq!(offset("Mode"), (1, 0, 1));
+    assert_eq!(offset("Letter"), (2, 0, 1));
+    assert_eq!(offset("Count"), (4, 0, 2));
+    assert_eq!(offset("C"), (6, 0, 1));
+    // arrays start at an even byte, the bools are packed
+    assert_eq!(offset("Flags"), (8, 0, 2));
+    assert_eq!(offset("Flags[-1]"), (8, 0, 1));
+    assert_eq!(offset("Flags[7]"), (9, 0, 1));
+    assert_eq!(offset("Flags[8]"), (9, 1, 1));
+    assert_eq!(offset("Name"), (10, 0, 5));
+    assert_eq!(offset("Total"), (16, 0, 4));
+    assert_eq!(offset("Matrix"), (20, 0, 4));
+    assert_eq!(offset("Matrix[1,1]"), (21, 0, 1));
+    assert_eq!(offset("Matrix[2,0]"), (22, 0, 1));
+    assert_eq!(offset("Recipe"), (24, 0, 14));
+    assert_eq!(offset("Recipe.Step"), (24, 0, 1));
+    assert_eq!(offset("Recipe.Motors"), (26, 0, 12));
+    assert_eq!(offset("Recipe.Motors[2]"), (32, 0, 6));
+    assert_eq!(offset("Recipe.Motors[2].Running"), (32, 0, 1));
+    assert_eq!(offset("Recipe.Motors[2].Speed"), (34, 0, 4));
+    assert_eq!(offset("Last"), (38, 0, 1));
+    assert_eq!(layout.size, 40);
+
+    let area = layout.get("Recipe.Motors[1].Speed").unwrap().area;
+    assert_eq!(area.to_string(), "P#DB5.DBX28.0 REAL 1");
+    assert_eq!(layout.areas().len(), layout.members.len());
+    assert_eq!(layout.areas()[0].0, "A");
+
+    let invalid = DataType::Array {
+        dims: vec![(2, 1)],
+        element: Box::new(DataType::Int),
+    };
+    assert!(DbLayout::compute(1, &[Member::new("X", invalid)]).is_err());
+    assert!(DbLayout::compute(1, &[Member::new("X", DataType::String(0))]).is_err());
+
+    let huge = DataType::Array {
+        dims: vec![(0, 2_000_000_000)],
+        element: Box::new(DataType::Bool),
+    };
+    match DbLayout::compute(1, &[Member::new("X", huge)]) {
+        Err(Error::InvalidAddr(_)) => {}
+        other => panic!("expected invalid addr got {:?}", other),
+    }
+    let overflow = DataType::Array {
+        dims: vec![(i32::MIN, i32::MAX); 3],
+        element: Box::new(DataType::Int),
+    };
+    match DbLayout::compute(1, &[Member::new("X", overflow)]) {
+        Err(Error::InvalidInput { .. }) => {}
+        other => panic!("expected invalid input got {:?}", other),
+    }
+}
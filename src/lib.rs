@@ -3,19 +3,32 @@
 // This software may be modified and distributed under the terms
 // of the BSD license. See the LICENSE file for details.
 
+mod address;
 mod client;
 mod constant;
+pub mod de;
+pub mod diagnostic;
 pub mod error;
 pub mod field;
+pub mod layout;
+pub mod ser;
+pub mod source;
+pub mod szl;
+pub mod tag;
 pub mod tcp;
 pub mod transport;
+pub mod value;
 
 use crate::transport::Connection;
-pub use client::Client;
+pub use client::{CPInfo, Client, CpuInfo, Identity};
 pub use constant::{Area, BitAddr, DataSizeType};
+pub use layout::S7Layout;
+#[cfg(feature = "derive")]
+pub use s7_derive::S7Layout;
 use serde::{Deserialize, Serialize};
 use std::net::Ipv4Addr;
 use std::time::Duration;
+pub use value::S7Value;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollectParam {
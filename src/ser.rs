@@ -0,0 +1,490 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Serializes values to the memory layout of standard (non-optimized) data blocks
+//!
+//! - numbers are big-endian, `i8`/`u8` take one byte, `i16`/`u16` two, `i32`/`u32`/`f32` four
+//!   and `i64`/`u64`/`f64` eight bytes
+//! - consecutive bools are packed into the bits of one byte
+//! - `u8`, `i8` and `char` start at the next whole byte, every other value at the next even byte
+//! - structs, tuples and arrays start and end at an even byte
+//! - `String` is a STRING[254], use `FixedString` for other lengths
+//!
+//! Options, maps and enums have no S7 representation and are rejected.
+//!
+//! # Examples
+//!
+//! ```
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Motor {
+//!     running: bool,
+//!     fault: bool,
+//!     speed: i16,
+//! }
+//!
+//! let bytes = s7::ser::to_bytes(&Motor { running: true, fault: true, speed: 300 }).unwrap();
+//! assert_eq!(bytes, vec![0b11, 0, 0x01, 0x2C]);
+//! ```
+
+use crate::error::Error;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeTuple};
+use std::fmt;
+
+/// max length of a `String`, STRING without a length is a STRING[254] in Step7
+pub(crate) const DEFAULT_STRING_LEN: usize = 254;
+
+/// serializes `value` to the bytes of a data block
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut serializer = Serializer::default();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
+
+/// S7 STRING with `N` characters at most, 0 < `N` < 255
+///
+/// Human readable formats like json see a plain string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FixedString<const N: usize>(pub String);
+
+impl<const N: usize> Serialize for FixedString<N> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return serializer.serialize_str(&self.0);
+        }
+        let bytes = string_bytes(&self.0, N).map_err(ser::Error::custom)?;
+        // a STRING is laid out like an array of bytes
+        let mut tuple = serializer.serialize_tuple(bytes.len())?;
+        for b in bytes.iter() {
+            tuple.serialize_element(b)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for FixedString<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let value = String::deserialize(deserializer)?;
+            if value.chars().count() > N {
+                return Err(de::Error::custom(format!(
+                    "{:?} does not fit a STRING[{}]",
+                    value, N
+                )));
+            }
+            return Ok(FixedString(value));
+        }
+        deserializer.deserialize_tuple(N + 2, FixedStringVisitor::<N>)
+    }
+}
+
+struct FixedStringVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for FixedStringVisitor<N> {
+    type Value = FixedString<N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a STRING[{}]", N)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(N + 2);
+        for i in 0..N + 2 {
+            match seq.next_element::<u8>()? {
+                Some(b) => bytes.push(b),
+                None => return Err(de::Error::invalid_length(i, &self)),
+            }
+        }
+        if bytes[0] as usize != N {
+            return Err(de::Error::custom(format!(
+                "expected a STRING[{}] got a STRING[{}]",
+                N, bytes[0]
+            )));
+        }
+        let value = string_from_bytes(&bytes).map_err(de::Error::custom)?;
+        Ok(FixedString(value))
+    }
+}
+
+/// header and latin-1 characters of a STRING[max_len], padded to `max_len`
+fn string_bytes(value: &str, max_len: usize) -> Result<Vec<u8>, Error> {
+    let len = value.chars().count();
+    if max_len == 0 || max_len > DEFAULT_STRING_LEN {
+        return Err(Error::Serde(format!(
+            "STRING[{}] must have 1 to {} characters",
+            max_len, DEFAULT_STRING_LEN
+        )));
+    }
+    if len > max_len || value.chars().any(|c| c as u32 > 0xFF) {
+        return Err(Error::Serde(format!(
+            "{:?} does not fit a STRING[{}]",
+            value, max_len
+        )));
+    }
+    let mut bytes = vec![0u8; max_len + 2];
+    bytes[0] = max_len as u8;
+    bytes[1] = len as u8;
+    for (i, c) in value.chars().enumerate() {
+        bytes[2 + i] = c as u8;
+    }
+    Ok(bytes)
+}
+
+/// the characters of a STRING, `bytes` starts with the header
+pub(crate) fn string_from_bytes(bytes: &[u8]) -> Result<String, Error> {
+    let (max_len, len) = (bytes[0] as usize, bytes[1] as usize);
+    if len > max_len || bytes.len() < max_len + 2 {
+        return Err(Error::Serde(format!(
+            "invalid STRING[{}] with length {}",
+            max_len, len
+        )));
+    }
+    Ok(bytes[2..2 + len].iter().map(|b| *b as char).collect())
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::Serde(format!("{} has no S7 representation", what))
+}
+
+/// writes values in the S7 data block layout
+#[derive(Debug, Default)]
+pub struct Serializer {
+    output: Vec<u8>,
+    /// next bit of the last byte to pack a bool into, 0 starts a new byte
+    bit: u8,
+}
+
+impl Serializer {
+    fn align_byte(&mut self) {
+        self.bit = 0;
+    }
+
+    fn align_word(&mut self) {
+        self.align_byte();
+        if self.output.len() % 2 == 1 {
+            self.output.push(0);
+        }
+    }
+
+    fn byte(&mut self, value: u8) -> Result<(), Error> {
+        self.align_byte();
+        self.output.push(value);
+        Ok(())
+    }
+
+    fn word(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.align_word();
+        self.output.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = ser::Impossible<(), Error>;
+    type SerializeMap = ser::Impossible<(), Error>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        if self.bit == 0 {
+            self.output.push(0);
+        }
+        if v {
+            if let Some(last) = self.output.last_mut() {
+                *last |= 1 << self.bit;
+            }
+        }
+        self.bit = (self.bit + 1) % 8;
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.byte(v as u8)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.word(&v.to_be_bytes())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.word(&v.to_be_bytes())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.word(&v.to_be_bytes())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.byte(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.word(&v.to_be_bytes())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.word(&v.to_be_bytes())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.word(&v.to_be_bytes())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.word(&v.to_be_bytes())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.word(&v.to_be_bytes())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        if v as u32 > 0xFF {
+            return Err(Error::Serde(format!("{:?} is not a latin-1 character", v)));
+        }
+        self.byte(v as u8)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        let bytes = string_bytes(v, DEFAULT_STRING_LEN)?;
+        self.word(&bytes)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.word(v)?;
+        self.align_word();
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(unsupported("Option"))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<(), Error> {
+        Err(unsupported("Option"))
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        Err(unsupported(name))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(unsupported(name))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        self.align_word();
+        Ok(Compound { ser: self })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        self.align_word();
+        Ok(Compound { ser: self })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.align_word();
+        Ok(Compound { ser: self })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(unsupported(name))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(unsupported("map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        self.align_word();
+        Ok(Compound { ser: self })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(unsupported(name))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// members of a struct, tuple or array, the next value starts at an even byte
+pub struct Compound<'a> {
+    ser: &'a mut Serializer,
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.ser.align_word();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.ser.align_word();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.ser.align_word();
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.ser.align_word();
+        Ok(())
+    }
+}
+
+#[test]
+fn test_serialize() {
+    #[derive(serde::Serialize)]
+    struct Axis {
+        enabled: bool,
+        position: f32,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Machine {
+        running: bool,
+        fault: bool,
+        mode: u8,
+        letter: char,
+        speed: i16,
+        flags: [bool; 10],
+        axis: Axis,
+        name: FixedString<3>,
+        counter: u8,
+        total: u64,
+    }
+
+    let machine = Machine {
+        running: true,
+        fault: false,
+        mode: 2,
+        letter: 'A',
+        speed: -2,
+        flags: [true; 10],
+        axis: Axis {
+            enabled: true,
+            position: 53.5,
+        },
+        name: FixedString("ab".to_string()),
+        counter: 9,
+        total: 1,
+    };
+    let bytes = to_bytes(&machine).unwrap();
+    assert_eq!(
+        bytes,
+        vec![
+            0b01, 2, b'A', 0, 0xFF, 0xFE, // bits, mode, letter, speed
+            0xFF, 0b11, // flags, padded to a word
+            1, 0, 66, 86, 0, 0, // axis
+            3, 2, b'a', b'b', 0, 0, // STRING[3] padded to a word
+            9, 0, 0, 0, 0, 0, 0, 0, 0, 1, // counter, total
+        ]
+    );
+
+    assert_eq!(to_bytes("hi").unwrap().len(), 256);
+    assert!(to_bytes(&FixedString::<1>("ab".to_string())).is_err());
+    assert!(to_bytes(&'€').is_err());
+    assert!(to_bytes(&Some(1u8)).is_err());
+
+    // other formats see a plain string
+    let json = serde_json::to_string(&FixedString::<3>("ab".to_string())).unwrap();
+    assert_eq!(json, "\"ab\"");
+}
@@ -0,0 +1,710 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Parses data block and UDT sources exported by Step7 or TIA Portal
+//!
+//! Only the declaration and the `S7_Optimized_Access` attribute of data blocks are used:
+//! initial values, the `BEGIN` section, other attributes and comments are skipped. UDTs have to be added before the blocks using them, either in the
+//! same source or with an earlier call to `Source::add`.
+//!
+//! # Examples
+//!
+//! ```
+//! use s7::source::Source;
+//!
+//! let source = Source::parse(r#"
+//! TYPE "Motor"
+//! VERSION : 0.1
+//!    STRUCT
+//!       Running : Bool;
+//!       Speed : Real;
+//!    END_STRUCT;
+//! END_TYPE
+//!
+//! DATA_BLOCK "Line"
+//! { S7_Optimized_Access := 'FALSE' }
+//! VERSION : 0.1
+//!    STRUCT
+//!       Recipe : Struct
+//!          Speed : Array[0..9] of Int;
+//!          Name : String[32] := 'default';
+//!       END_STRUCT;
+//!       Motor : "Motor";
+//!    END_STRUCT;
+//! BEGIN
+//!    Recipe.Speed[0] := 100;
+//! END_DATA_BLOCK
+//! "#).unwrap();
+//!
+//! let layout = source.data_block("Line").unwrap().layout(10).unwrap();
+//! assert_eq!(layout.get("Recipe.Speed[3]").unwrap().area.to_string(), "P#DB10.DBX6.0 INT 1");
+//! assert_eq!(layout.get("Motor.Speed").unwrap().area.to_string(), "P#DB10.DBX56.0 REAL 1");
+//! ```
+
+use crate::error::Error;
+use crate::layout::{DataType, DbLayout, Member};
+
+/// UDTs and data blocks declared in sources
+#[derive(Debug, Clone, Default)]
+pub struct Source {
+    /// every UDT as `DataType::Udt`
+    pub udts: Vec<DataType>,
+    pub data_blocks: Vec<DataBlock>,
+}
+
+/// data block declared in a source
+#[derive(Debug, Clone)]
+pub struct DataBlock {
+    /// symbol or `DB n`
+    pub name: String,
+    /// number of an absolute name like `DB 10`
+    pub number: Option<u16>,
+    /// `S7_Optimized_Access := 'TRUE'`, the members of optimized blocks have no fixed offsets
+    pub optimized: bool,
+    pub members: Vec<Member>,
+}
+
+impl DataBlock {
+    /// offsets of the members when the block is stored as data block `db`,
+    /// optimized blocks can not be accessed by offset and return an error
+    pub fn layout(&self, db: u16) -> Result<DbLayout, Error> {
+        if self.optimized {
+            return Err(Error::InvalidInput {
+                input: format!(
+                    "data block {} uses optimized access, its members have no offsets",
+                    self.name
+                ),
+            });
+        }
+        DbLayout::compute(db, &self.members)
+    }
+}
+
+impl Source {
+    /// parses a single source
+    pub fn parse(text: &str) -> Result<Source, Error> {
+        let mut source = Source::default();
+        source.add(text)?;
+        Ok(source)
+    }
+
+    /// parses another source, it can use the UDTs added before
+    pub fn add(&mut self, text: &str) -> Result<(), Error> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+            source: self,
+        };
+        parser.parse()
+    }
+
+    /// the UDT with symbol or absolute name `name`, like `"Motor"` or `UDT 5`
+    pub fn udt(&self, name: &str) -> Option<&DataType> {
+        let name = name.trim_matches('"');
+        self.udts.iter().find(|udt| match udt {
+            DataType::Udt(udt_name, _) => udt_name == name,
+            _ => false,
+        })
+    }
+
+    /// the data block with symbol or absolute name `name`, like `"Recipe"` or `DB 10`
+    pub fn data_block(&self, name: &str) -> Option<&DataBlock> {
+        let name = name.trim_matches('"');
+        self.data_blocks.iter().find(|block| block.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// keyword or identifier
+    Ident(String),
+    /// symbol in double quotes
+    Symbol(String),
+    /// text in single quotes
+    Text(String),
+    Number(String),
+    Punct(&'static str),
+    /// `{ name := 'value'; ... }` as name and value pairs
+    Attributes(Vec<(String, String)>),
+    /// anything else, only found in initial values
+    Other(char),
+}
+
+/// token and the line it starts on
+type Spanned = (Token, usize);
+
+fn tokenize(text: &str) -> Result<Vec<Spanned>, Error> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line) = (0, 1);
+    let error = |line: usize, reason: &str| Error::InvalidInput {
+        input: format!("line {}: {}", line, reason),
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let start = line;
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            _ if c.is_whitespace() => i += 1,
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '(' if next == Some('*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&')')) {
+                    line += (chars[i] == '\n') as usize;
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(error(start, "unterminated comment"));
+                }
+                i += 2;
+            }
+            // attributes like { S7_Optimized_Access := 'FALSE' }
+            '{' => {
+                let begin = i + 1;
+                while i < chars.len() && chars[i] != '}' {
+                    line += (chars[i] == '\n') as usize;
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(error(start, "unterminated attribute"));
+                }
+                let inner: String = chars[begin..i].iter().collect();
+                i += 1;
+                let mut attributes = Vec::new();
+                for pair in tokenize(&inner)?.split(|(token, _)| *token == Token::Punct(";")) {
+                    if let [(Token::Ident(name), _), (Token::Punct(":="), _), (Token::Text(value), _)] =
+                        pair
+                    {
+                        attributes.push((name.clone(), value.clone()));
+                    }
+                }
+                tokens.push((Token::Attributes(attributes), start));
+            }
+            '"' | '\'' => {
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None | Some('\n') => return Err(error(start, "unterminated quote")),
+                        // quotes are escaped by doubling them
+                        Some(q) if *q == c && chars.get(i + 1) == Some(&c) => {
+                            value.push(c);
+                            i += 2;
+                        }
+                        Some(q) if *q == c => {
+                            i += 1;
+                            break;
+                        }
+                        Some(other) => {
+                            value.push(*other);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push((
+                    if c == '"' {
+                        Token::Symbol(value)
+                    } else {
+                        Token::Text(value)
+                    },
+                    start,
+                ));
+            }
+            _ if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let begin = i;
+                i += 1;
+                while i < chars.len() {
+                    let d = chars[i];
+                    let float = d == '.' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit());
+                    if d.is_ascii_alphanumeric() || d == '_' || d == '#' || float {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push((Token::Number(chars[begin..i].iter().collect()), start));
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let begin = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '#')
+                {
+                    i += 1;
+                }
+                tokens.push((Token::Ident(chars[begin..i].iter().collect()), start));
+            }
+            _ => {
+                let punct = match (c, next) {
+                    ('.', Some('.')) => Some(".."),
+                    (':', Some('=')) => Some(":="),
+                    _ => None,
+                };
+                if let Some(punct) = punct {
+                    tokens.push((Token::Punct(punct), start));
+                    i += 2;
+                    continue;
+                }
+                let punct = match c {
+                    ':' => Some(":"),
+                    ';' => Some(";"),
+                    '[' => Some("["),
+                    ']' => Some("]"),
+                    ',' => Some(","),
+                    '(' => Some("("),
+                    ')' => Some(")"),
+                    _ => None,
+                };
+                tokens.push((punct.map_or(Token::Other(c), Token::Punct), start));
+                i += 1;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Spanned>,
+    pos: usize,
+    source: &'a mut Source,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, reason: &str) -> Error {
+        Error::InvalidInput {
+            input: format!("line {}: {}", self.line(), reason),
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(token, _)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    /// whether the next token is the keyword `keyword`, keywords are case insensitive
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case(keyword))
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), Error> {
+        if !self.is_punct(punct) {
+            return Err(self.error(&format!("expected {:?} got {:?}", punct, self.peek())));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_number<T: std::str::FromStr>(&mut self) -> Result<T, Error> {
+        match self.next() {
+            Some(Token::Number(number)) => number
+                .parse()
+                .map_err(|_| self.error(&format!("invalid number {}", number))),
+            other => Err(self.error(&format!("expected a number got {:?}", other))),
+        }
+    }
+
+    /// skips tokens up to and including `keyword`
+    fn skip_to(&mut self, keyword: &str) -> Result<(), Error> {
+        while self.peek().is_some() {
+            if self.is_keyword(keyword) {
+                self.pos += 1;
+                return Ok(());
+            }
+            self.pos += 1;
+        }
+        Err(self.error(&format!("missing {}", keyword)))
+    }
+
+    fn parse(&mut self) -> Result<(), Error> {
+        while self.peek().is_some() {
+            if self.is_keyword("TYPE") {
+                self.pos += 1;
+                self.parse_udt()?;
+            } else if self.is_keyword("DATA_BLOCK") {
+                self.pos += 1;
+                self.parse_data_block()?;
+            } else {
+                // other blocks in the same source
+                self.pos += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// symbol like `"Motor"` or absolute name like `UDT 5`
+    fn parse_block_name(&mut self) -> Result<(String, Option<u16>), Error> {
+        match self.next() {
+            Some(Token::Symbol(name)) => Ok((name, None)),
+            Some(Token::Ident(kind)) => {
+                let number = self.expect_number()?;
+                Ok((format!("{} {}", kind.to_uppercase(), number), Some(number)))
+            }
+            other => Err(self.error(&format!("expected a block name got {:?}", other))),
+        }
+    }
+
+    /// skips the block header up to `STRUCT` or a block name and returns its attributes
+    fn skip_header(&mut self) -> Vec<(String, String)> {
+        let mut attributes = Vec::new();
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Symbol(_) => break,
+                Token::Ident(ident)
+                    if ident.eq_ignore_ascii_case("STRUCT")
+                        || ident.eq_ignore_ascii_case("UDT")
+                        || ident.eq_ignore_ascii_case("FB")
+                        || ident.eq_ignore_ascii_case("SFB") =>
+                {
+                    break
+                }
+                Token::Attributes(pairs) => {
+                    attributes.extend(pairs.iter().cloned());
+                    self.pos += 1;
+                }
+                // the title is free text up to the end of the line
+                Token::Ident(ident) if ident.eq_ignore_ascii_case("TITLE") => {
+                    let line = self.line();
+                    while self.peek().is_some() && self.line() == line {
+                        self.pos += 1;
+                    }
+                }
+                _ => self.pos += 1,
+            }
+        }
+        attributes
+    }
+
+    fn parse_udt(&mut self) -> Result<(), Error> {
+        let (name, _) = self.parse_block_name()?;
+        self.skip_header();
+        if !self.is_keyword("STRUCT") {
+            return Err(self.error(&format!("expected STRUCT in type {}", name)));
+        }
+        self.pos += 1;
+        let members = self.parse_struct()?;
+        self.skip_to("END_TYPE")?;
+        self.source.udts.retain(|udt| match udt {
+            DataType::Udt(udt_name, _) => *udt_name != name,
+            _ => true,
+        });
+        self.source.udts.push(DataType::Udt(name, members));
+        Ok(())
+    }
+
+    fn parse_data_block(&mut self) -> Result<(), Error> {
+        let (name, number) = self.parse_block_name()?;
+        let optimized = self.skip_header().iter().any(|(attribute, value)| {
+            attribute.eq_ignore_ascii_case("S7_Optimized_Access")
+                && value.eq_ignore_ascii_case("TRUE")
+        });
+        let members = if self.is_keyword("STRUCT") {
+            self.pos += 1;
+            self.parse_struct()?
+        } else {
+            // a data block with the type of a UDT
+            match self.parse_type()? {
+                DataType::Udt(_, members) => members,
+                _ => return Err(self.error(&format!("invalid type of data block {}", name))),
+            }
+        };
+        self.skip_to("END_DATA_BLOCK")?;
+        self.source.data_blocks.retain(|block| block.name != name);
+        self.source.data_blocks.push(DataBlock {
+            name,
+            number,
+            optimized,
+            members,
+        });
+        Ok(())
+    }
+
+    /// members up to and including `END_STRUCT`
+    fn parse_struct(&mut self) -> Result<Vec<Member>, Error> {
+        let mut members = Vec::new();
+        loop {
+            if self.is_keyword("END_STRUCT") {
+                self.pos += 1;
+                return Ok(members);
+            }
+            let name = match self.next() {
+                Some(Token::Ident(name)) | Some(Token::Symbol(name)) => name,
+                None => return Err(self.error("missing END_STRUCT")),
+                other => return Err(self.error(&format!("expected a member got {:?}", other))),
+            };
+            // member attributes like { S7_SetPoint := 'False' }
+            if let Some(Token::Attributes(_)) = self.peek() {
+                self.pos += 1;
+            }
+            self.expect_punct(":")?;
+            let data_type = self.parse_type()?;
+            self.skip_initial_value()?;
+            members.push(Member { name, data_type });
+        }
+    }
+
+    /// skips `:= value` and the closing `;`
+    fn skip_initial_value(&mut self) -> Result<(), Error> {
+        if self.is_punct(":=") {
+            while !self.is_punct(";") {
+                if self.next().is_none() {
+                    return Err(self.error("missing ;"));
+                }
+            }
+        }
+        self.expect_punct(";")
+    }
+
+    fn parse_type(&mut self) -> Result<DataType, Error> {
+        let ident = match self.next() {
+            Some(Token::Ident(ident)) => ident.to_uppercase(),
+            Some(Token::Symbol(name)) => return self.lookup_udt(&name),
+            other => return Err(self.error(&format!("expected a type got {:?}", other))),
+        };
+        let data_type = match ident.as_str() {
+            "BOOL" => DataType::Bool,
+            "BYTE" => DataType::Byte,
+            "CHAR" => DataType::Char,
+            "SINT" => DataType::SInt,
+            "USINT" => DataType::USInt,
+            "WORD" => DataType::Word,
+            "INT" => DataType::Int,
+            "UINT" => DataType::UInt,
+            "DWORD" => DataType::DWord,
+            "DINT" => DataType::DInt,
+            "UDINT" => DataType::UDInt,
+            "REAL" => DataType::Real,
+            "LWORD" => DataType::LWord,
+            "LINT" => DataType::LInt,
+            "ULINT" => DataType::ULInt,
+            "LREAL" => DataType::LReal,
+            "S5TIME" => DataType::S5Time,
+            "TIME" => DataType::Time,
+            "DATE" => DataType::Date,
+            "TIME_OF_DAY" | "TOD" => DataType::TimeOfDay,
+            "DATE_AND_TIME" | "DT" => DataType::DateAndTime,
+            "DTL" => DataType::Dtl,
+            "STRING" => DataType::String(self.parse_string_len()?),
+            "WSTRING" => DataType::WString(self.parse_string_len()?),
+            "ARRAY" => {
+                self.expect_punct("[")?;
+                let mut dims = Vec::new();
+                loop {
+                    let lower = self.expect_number()?;
+                    self.expect_punct("..")?;
+                    let upper = self.expect_number()?;
+                    dims.push((lower, upper));
+                    if self.is_punct("]") {
+                        self.pos += 1;
+                        break;
+                    }
+                    self.expect_punct(",")?;
+                }
+                if !self.is_keyword("OF") {
+                    return Err(self.error("expected OF"));
+                }
+                self.pos += 1;
+                DataType::Array {
+                    dims,
+                    element: Box::new(self.parse_type()?),
+                }
+            }
+            "STRUCT" => DataType::Struct(self.parse_struct()?),
+            "UDT" => {
+                let number: u16 = self.expect_number()?;
+                self.lookup_udt(&format!("UDT {}", number))?
+            }
+            "FB" | "SFB" => {
+                return Err(self.error("instance data of function blocks is not supported"))
+            }
+            other => return Err(self.error(&format!("unsupported type {}", other))),
+        };
+        Ok(data_type)
+    }
+
+    /// `[n]` after STRING or WSTRING, 254 without a length
+    fn parse_string_len<T: std::str::FromStr + From<u8>>(&mut self) -> Result<T, Error> {
+        if !self.is_punct("[") {
+            return Ok(T::from(254));
+        }
+        self.pos += 1;
+        let len = self.expect_number()?;
+        self.expect_punct("]")?;
+        Ok(len)
+    }
+
+    fn lookup_udt(&self, name: &str) -> Result<DataType, Error> {
+        self.source
+            .udt(name)
+            .cloned()
+            .ok_or_else(|| self.error(&format!("unknown type {:?}", name)))
+    }
+}
+
+#[test]
+fn test_parse_step7_source() {
+    let text = "
+TYPE UDT 5
+VERSION : 0.1
+
+  STRUCT
+   Running : BOOL ; // motor is on
+   Speed : REAL ;
+  END_STRUCT ;
+END_TYPE
+
+DATA_BLOCK DB 10
+TITLE = Recipe data: \"Line\" 1
+AUTHOR : ME
+VERSION : 0.1
+
+  STRUCT
+   Active : BOOL := TRUE;
+   Speed : ARRAY  [-1 .. 8 ] OF INT ;
+   Name : STRING  [32 ] := 'it''s ok';
+   (* two
+      motors *)
+   Motors : ARRAY  [1 .. 2 ] OF UDT 5;
+   Data : STRUCT
+    Start : DATE_AND_TIME := DT#90-1-1-0:0:0.000;
+    Delay : TIME := T#1S;
+   END_STRUCT ;
+   Axes : ARRAY  [1 .. 2 ] OF STRUCT
+    Position : DINT ;
+    Enabled : BOOL ;
+   END_STRUCT ;
+   Crc : WORD ;
+  END_STRUCT ;
+BEGIN
+   Active := TRUE;
+   Speed[0] := 1;
+END_DATA_BLOCK
+";
+    let source = Source::parse(text).unwrap();
+    let block = source.data_block("DB 10").unwrap();
+    assert_eq!(block.number, Some(10));
+    assert_eq!(block.members.len(), 7);
+    assert_eq!(block.members[2].data_type, DataType::String(32));
+
+    let layout = block.layout(10).unwrap();
+    let offset = |path: &str| layout.get(path).unwrap().area.start();
+    assert_eq!(offset("Speed[-1]"), 2);
+    assert_eq!(offset("Name"), 22);
+    assert_eq!(offset("Motors[2].Speed"), 64);
+    assert_eq!(offset("Data.Start"), 68);
+    assert_eq!(offset("Data.Delay"), 76);
+    assert_eq!(offset("Axes[2].Position"), 86);
+    assert_eq!(offset("Axes[2].Enabled"), 90);
+    assert_eq!(offset("Crc"), 92);
+    assert_eq!(layout.size, 94);
+}
+
+#[test]
+fn test_parse_tia_source() {
+    let udt = r#"
+TYPE "Motor"
+VERSION : 0.1
+   STRUCT
+      Running { S7_SetPoint := 'False'} : Bool;   // on
+      Speed : Real := 1.5;
+   END_STRUCT;
+
+END_TYPE
+"#;
+    let mut source = Source::parse(udt).unwrap();
+    source
+        .add(
+            r#"
+DATA_BLOCK "Recipe"
+{ S7_Optimized_Access := 'FALSE' }
+VERSION : 0.1
+NON_RETAIN
+   STRUCT
+      "Step" : USInt;
+      Values : Array[0..1, 0..2] of LReal := [6(0.0)];
+      Text : WString;
+      Drives : Array[1..3] of "Motor";
+   END_STRUCT;
+
+BEGIN
+
+END_DATA_BLOCK
+
+DATA_BLOCK "Drive"
+{ S7_Optimized_Access := 'FALSE' }
+VERSION : 0.1
+NON_RETAIN
+"Motor"
+
+BEGIN
+   Speed := 2.0;
+END_DATA_BLOCK
+"#,
+        )
+        .unwrap();
+
+    let recipe = source.data_block("\"Recipe\"").unwrap();
+    assert_eq!(recipe.number, None);
+    let layout = recipe.layout(3).unwrap();
+    assert_eq!(layout.get("Values[1,2]").unwrap().area.start(), 2 + 5 * 8);
+    assert_eq!(layout.get("Text").unwrap().area.byte_len(), 512);
+    assert_eq!(
+        layout.get("Drives[3].Speed").unwrap().area.start(),
+        562 + 2 * 6 + 2
+    );
+
+    let drive = source.data_block("Drive").unwrap();
+    assert!(!drive.optimized);
+    assert_eq!(drive.layout(4).unwrap().size, 6);
+
+    let optimized = Source::parse(
+        r#"
+DATA_BLOCK "Settings"
+{ S7_Optimized_Access := 'TRUE' }
+VERSION : 0.1
+   STRUCT
+      Speed { S7_SetPoint := 'True'} : Real;
+   END_STRUCT;
+BEGIN
+END_DATA_BLOCK
+"#,
+    )
+    .unwrap();
+    let settings = optimized.data_block("Settings").unwrap();
+    assert!(settings.optimized);
+    match settings.layout(5) {
+        Err(Error::InvalidInput { .. }) => {}
+        other => panic!("expected invalid input got {:?}", other),
+    }
+
+    assert!(Source::parse(
+        "DATA_BLOCK \"X\" STRUCT A : \"Unknown\"; END_STRUCT; BEGIN END_DATA_BLOCK"
+    )
+    .is_err());
+    assert!(Source::parse("DATA_BLOCK \"X\" STRUCT A : Int; BEGIN END_DATA_BLOCK").is_err());
+    assert!(Source::parse("DATA_BLOCK \"X\" FB 1 BEGIN END_DATA_BLOCK").is_err());
+}
@@ -0,0 +1,596 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! System status lists (SZL) read with `Client::read_szl`
+//!
+//! A partial list is identified by its SZL-ID and index and consists of
+//! `number_of_data_record` records of `length_header` bytes each. The types of this module
+//! decode the records of the commonly used lists.
+
+use crate::error::Error;
+use crate::value::DateTime;
+use byteorder::{BigEndian, ByteOrder};
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+
+/// SZL-ID of the list of all SZL-IDs the CPU supports
+pub const SZL_ID_LIST: u16 = 0x0000;
+/// module identification, all records
+pub const SZL_ID_MODULE_IDENTIFICATION: u16 = 0x0011;
+/// module identification, the record with the index
+pub const SZL_ID_MODULE_IDENTIFICATION_RECORD: u16 = 0x0111;
+/// identification of all components
+pub const SZL_ID_COMPONENT_IDENTIFICATION: u16 = 0x001C;
+/// communication capabilities of the class given by the index
+pub const SZL_ID_COMMUNICATION_CAPABILITIES: u16 = 0x0131;
+/// protection levels, index 4
+pub const SZL_ID_PROTECTION: u16 = 0x0232;
+/// current mode and last mode transition
+pub const SZL_ID_MODE_TRANSITION: u16 = 0x0424;
+/// status of all LEDs
+pub const SZL_ID_LEDS: u16 = 0x0019;
+/// status of the module LEDs, on H systems with the rack of every LED
+pub const SZL_ID_MODULE_LEDS: u16 = 0x0074;
+/// status of all modules of a rack, the index is the rack number
+pub const SZL_ID_RACK_MODULE_STATUS: u16 = 0x0D91;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SzlHeader {
+    /// SZL-ID of the partial list
+    pub id: u16,
+    pub index: u16,
+    /// length of a record in bytes
+    pub length_header: u16,
+    pub number_of_data_record: u16,
+}
+
+/// partial list of a system status list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Szl {
+    pub header: SzlHeader,
+    /// all records without the header
+    pub data: Vec<u8>,
+}
+
+impl Szl {
+    /// the records of the list, incomplete records at the end are ignored
+    pub fn records(&self) -> impl Iterator<Item = &[u8]> {
+        let len = self.header.length_header as usize;
+        let count = if len == 0 {
+            0
+        } else {
+            self.header.number_of_data_record as usize
+        };
+        self.data.chunks_exact(len.max(1)).take(count)
+    }
+}
+
+/// records of at least `len` bytes
+fn records(szl: &Szl, len: usize) -> Result<impl Iterator<Item = &[u8]>, Error> {
+    if (szl.header.length_header as usize) < len {
+        return Err(Error::InvalidResponse {
+            reason: format!(
+                "SZL {:#06X} has records of {} bytes, expected {}",
+                szl.header.id, szl.header.length_header, len
+            ),
+            bytes: szl.data.clone(),
+        });
+    }
+    Ok(szl.records())
+}
+
+/// the first record of at least `len` bytes
+fn first_record(szl: &Szl, len: usize) -> Result<&[u8], Error> {
+    records(szl, len)?
+        .next()
+        .ok_or_else(|| Error::InvalidResponse {
+            reason: format!("SZL {:#06X} has no records", szl.header.id),
+            bytes: szl.data.clone(),
+        })
+}
+
+/// text of a record without the NUL and space padding
+pub(crate) fn text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+/// record of SZL 0x0011 or 0x0111
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleIdentification {
+    /// 1 module, 6 basic hardware, 7 basic firmware
+    pub index: u16,
+    pub order_number: String,
+    pub module_type: u16,
+    pub version_1: u16,
+    pub version_2: u16,
+}
+
+impl ModuleIdentification {
+    pub const INDEX_MODULE: u16 = 0x0001;
+    pub const INDEX_HARDWARE: u16 = 0x0006;
+    pub const INDEX_FIRMWARE: u16 = 0x0007;
+
+    pub fn decode(szl: &Szl) -> Result<Vec<ModuleIdentification>, Error> {
+        Ok(records(szl, 28)?
+            .map(|record| ModuleIdentification {
+                index: BigEndian::read_u16(&record[0..]),
+                order_number: text(&record[2..22]),
+                module_type: BigEndian::read_u16(&record[22..]),
+                version_1: BigEndian::read_u16(&record[24..]),
+                version_2: BigEndian::read_u16(&record[26..]),
+            })
+            .collect())
+    }
+
+    /// `V2.6.3` for firmware versions, the plain number for hardware versions
+    pub fn version(&self) -> String {
+        let [prefix, major] = self.version_1.to_be_bytes();
+        let [minor, patch] = self.version_2.to_be_bytes();
+        if prefix == b'V' {
+            format!("V{}.{}.{}", major, minor, patch)
+        } else {
+            self.version_2.to_string()
+        }
+    }
+}
+
+/// SZL 0x001C, empty texts and `None` for indexes the module does not provide
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ComponentIdentification {
+    /// name of the automation system
+    pub as_name: String,
+    pub module_name: String,
+    pub plant_id: String,
+    pub copyright: String,
+    pub serial_number: String,
+    pub module_type_name: String,
+    pub memory_card_serial_number: String,
+    pub manufacturer_id: Option<u16>,
+    pub profile_id: Option<u16>,
+    pub profile_specific_type: Option<u16>,
+    pub oem_copyright: String,
+    pub oem_id: Option<u16>,
+    pub oem_additional_id: Option<u32>,
+    pub location: String,
+}
+
+impl ComponentIdentification {
+    pub fn decode(szl: &Szl) -> Result<ComponentIdentification, Error> {
+        let mut component = ComponentIdentification::default();
+        for record in records(szl, 34)? {
+            let data = &record[2..34];
+            match BigEndian::read_u16(record) {
+                0x0001 => component.as_name = text(&data[..24]),
+                0x0002 => component.module_name = text(&data[..24]),
+                0x0003 => component.plant_id = text(data),
+                0x0004 => component.copyright = text(&data[..26]),
+                0x0005 => component.serial_number = text(&data[..24]),
+                0x0007 => component.module_type_name = text(data),
+                0x0008 => component.memory_card_serial_number = text(data),
+                0x0009 => {
+                    component.manufacturer_id = Some(BigEndian::read_u16(&data[0..]));
+                    component.profile_id = Some(BigEndian::read_u16(&data[2..]));
+                    component.profile_specific_type = Some(BigEndian::read_u16(&data[4..]));
+                }
+                0x000A => {
+                    component.oem_copyright = text(&data[..26]);
+                    component.oem_id = Some(BigEndian::read_u16(&data[26..]));
+                    component.oem_additional_id = Some(BigEndian::read_u32(&data[28..]));
+                }
+                0x000B => component.location = text(data),
+                _ => {}
+            }
+        }
+        Ok(component)
+    }
+}
+
+/// record of SZL 0x0131
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CommunicationCapabilities {
+    /// index 1
+    General {
+        max_pdu_length: u16,
+        max_connections: u16,
+        max_mpi_rate: u32,
+        max_bus_rate: u32,
+    },
+    /// index 2 test and commissioning, 3 operator interface, 4 OMS, 5 diagnostics,
+    /// 6 data exchange with SFBs, 7 global data, 8 time functions and others:
+    /// the bit field of the supported functions followed by the class specific data
+    Functions {
+        index: u16,
+        functions: [u8; 8],
+        data: Vec<u8>,
+    },
+}
+
+impl CommunicationCapabilities {
+    pub fn decode(szl: &Szl) -> Result<Vec<CommunicationCapabilities>, Error> {
+        Ok(records(szl, 14)?
+            .map(|record| match BigEndian::read_u16(record) {
+                0x0001 => CommunicationCapabilities::General {
+                    max_pdu_length: BigEndian::read_u16(&record[2..]),
+                    max_connections: BigEndian::read_u16(&record[4..]),
+                    max_mpi_rate: BigEndian::read_u32(&record[6..]),
+                    max_bus_rate: BigEndian::read_u32(&record[10..]),
+                },
+                index => CommunicationCapabilities::Functions {
+                    index,
+                    functions: record[2..10].try_into().unwrap(),
+                    data: record[10..].to_vec(),
+                },
+            })
+            .collect())
+    }
+
+    pub fn index(&self) -> u16 {
+        match self {
+            CommunicationCapabilities::General { .. } => 0x0001,
+            CommunicationCapabilities::Functions { index, .. } => *index,
+        }
+    }
+}
+
+/// position of the mode selector
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModeSelector {
+    Run,
+    RunP,
+    Stop,
+    MemoryReset,
+    /// no mode selector or an unknown position
+    Undefined(u16),
+}
+
+impl From<u16> for ModeSelector {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => ModeSelector::Run,
+            2 => ModeSelector::RunP,
+            3 => ModeSelector::Stop,
+            4 => ModeSelector::MemoryReset,
+            other => ModeSelector::Undefined(other),
+        }
+    }
+}
+
+/// SZL 0x0232 index 4
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Protection {
+    /// protection level set with the mode selector, 1 to 3
+    pub key_switch_level: u16,
+    /// protection level set in the parameters, 0 for none
+    pub parameter_level: u16,
+    /// valid protection level of the CPU
+    pub level: u16,
+    pub mode_selector: ModeSelector,
+    /// startup switch, 1 CRST, 2 WRST, 0 none
+    pub startup_switch: u16,
+}
+
+impl Protection {
+    pub const INDEX: u16 = 0x0004;
+
+    pub fn decode(szl: &Szl) -> Result<Protection, Error> {
+        let record = first_record(szl, 12)?;
+        Ok(Protection {
+            key_switch_level: BigEndian::read_u16(&record[2..]),
+            parameter_level: BigEndian::read_u16(&record[4..]),
+            level: BigEndian::read_u16(&record[6..]),
+            mode_selector: ModeSelector::from(BigEndian::read_u16(&record[8..])),
+            startup_switch: BigEndian::read_u16(&record[10..]),
+        })
+    }
+}
+
+/// operating mode of a mode transition
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperatingMode {
+    /// STOP by an update, memory reset, self initialization or internal
+    Stop(u8),
+    /// STARTUP by a complete restart, cold restart or restart
+    Startup(u8),
+    Run,
+    RunRedundant,
+    Halt,
+    Coupling,
+    Updating,
+    Defective,
+    ErrorSearch,
+    Off,
+    Unknown(u8),
+}
+
+impl From<u8> for OperatingMode {
+    fn from(value: u8) -> Self {
+        match value {
+            1..=4 => OperatingMode::Stop(value),
+            5..=7 => OperatingMode::Startup(value),
+            8 => OperatingMode::Run,
+            9 => OperatingMode::RunRedundant,
+            0x0A => OperatingMode::Halt,
+            0x0B => OperatingMode::Coupling,
+            0x0C => OperatingMode::Updating,
+            0x0D => OperatingMode::Defective,
+            0x0E => OperatingMode::ErrorSearch,
+            0x0F => OperatingMode::Off,
+            other => OperatingMode::Unknown(other),
+        }
+    }
+}
+
+/// SZL 0x0424
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModeTransition {
+    pub event_id: u16,
+    pub mode: OperatingMode,
+    pub previous_mode: OperatingMode,
+    /// time of the transition, `None` when the CPU has not set it
+    pub time: Option<DateTime>,
+}
+
+impl ModeTransition {
+    pub fn decode(szl: &Szl) -> Result<ModeTransition, Error> {
+        let record = first_record(szl, 20)?;
+        Ok(ModeTransition {
+            event_id: BigEndian::read_u16(record),
+            mode: OperatingMode::from(record[3] & 0x0F),
+            previous_mode: OperatingMode::from(record[3] >> 4),
+            time: DateTime::from_bcd_bytes(record[12..20].try_into().unwrap()).ok(),
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LedFlash {
+    None,
+    Normal,
+    Slow,
+    Unknown(u8),
+}
+
+/// record of SZL 0x0019 or 0x0074
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Led {
+    /// rack of the CPU for H systems, 0 otherwise
+    pub rack: u8,
+    pub id: u8,
+    pub on: bool,
+    pub flash: LedFlash,
+}
+
+impl Led {
+    pub fn decode(szl: &Szl) -> Result<Vec<Led>, Error> {
+        Ok(records(szl, 4)?
+            .map(|record| Led {
+                rack: record[0] & 0x07,
+                id: record[1],
+                on: record[2] == 1,
+                flash: match record[3] {
+                    0 => LedFlash::None,
+                    1 => LedFlash::Normal,
+                    2 => LedFlash::Slow,
+                    other => LedFlash::Unknown(other),
+                },
+            })
+            .collect())
+    }
+
+    /// label of the LED
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self.id {
+            0x01 => "SF",
+            0x02 => "INTF",
+            0x03 => "EXTF",
+            0x04 => "RUN",
+            0x05 => "STOP",
+            0x06 => "FRCE",
+            0x07 => "CRST",
+            0x08 => "BAF",
+            0x09 => "USR",
+            0x0A => "USR1",
+            0x0B => "BUS1F",
+            0x0C => "BUS2F",
+            0x0D => "REDF",
+            0x0E => "MSTR",
+            0x0F => "RACK0",
+            0x10 => "RACK1",
+            0x11 => "RACK2",
+            0x12 => "IFM1F",
+            0x13 => "IFM2F",
+            _ => return None,
+        })
+    }
+}
+
+/// record of SZL 0x0D91
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleStatus {
+    /// rack number, or DP master system and station number
+    pub address_1: u16,
+    /// slot and submodule slot
+    pub address_2: u16,
+    pub logical_address: u16,
+    pub expected_type: u16,
+    pub actual_type: u16,
+    /// I/O status bits, see `fault`, `exists`, `unavailable` and `disabled`
+    pub io_status: u16,
+    /// area ID and module width
+    pub area: u16,
+}
+
+impl ModuleStatus {
+    pub fn decode(szl: &Szl) -> Result<Vec<ModuleStatus>, Error> {
+        Ok(records(szl, 16)?
+            .map(|record| ModuleStatus {
+                address_1: BigEndian::read_u16(&record[0..]),
+                address_2: BigEndian::read_u16(&record[2..]),
+                logical_address: BigEndian::read_u16(&record[4..]),
+                expected_type: BigEndian::read_u16(&record[6..]),
+                actual_type: BigEndian::read_u16(&record[8..]),
+                io_status: BigEndian::read_u16(&record[12..]),
+                area: BigEndian::read_u16(&record[14..]),
+            })
+            .collect())
+    }
+
+    pub fn fault(&self) -> bool {
+        self.io_status & 0x01 != 0
+    }
+
+    pub fn exists(&self) -> bool {
+        self.io_status & 0x02 != 0
+    }
+
+    pub fn unavailable(&self) -> bool {
+        self.io_status & 0x04 != 0
+    }
+
+    pub fn disabled(&self) -> bool {
+        self.io_status & 0x08 != 0
+    }
+
+    pub fn slot(&self) -> u8 {
+        (self.address_2 >> 8) as u8
+    }
+}
+
+#[test]
+fn test_records() {
+    let mut szl = Szl {
+        header: SzlHeader {
+            id: SZL_ID_LIST,
+            index: 0,
+            length_header: 2,
+            number_of_data_record: 3,
+        },
+        data: vec![0x00, 0x11, 0x00, 0x1C, 0x01, 0x31, 0xFF],
+    };
+    let records: Vec<&[u8]> = szl.records().collect();
+    assert_eq!(records, vec![&[0x00, 0x11], &[0x00, 0x1C], &[0x01, 0x31]]);
+
+    szl.header.number_of_data_record = 5;
+    assert_eq!(szl.records().count(), 3);
+    szl.header.length_header = 0;
+    assert_eq!(szl.records().count(), 0);
+
+    let json = serde_json::to_string(&szl).unwrap();
+    assert_eq!(serde_json::from_str::<Szl>(&json).unwrap(), szl);
+}
+
+#[cfg(test)]
+fn szl(id: u16, length_header: u16, records: &[&[u8]]) -> Szl {
+    Szl {
+        header: SzlHeader {
+            id,
+            index: 0,
+            length_header,
+            number_of_data_record: records.len() as u16,
+        },
+        data: records.concat(),
+    }
+}
+
+#[test]
+fn test_decode_identification() {
+    let mut module = vec![0x00, 0x01];
+    module.extend_from_slice(b"6ES7 315-2EH14-0AB0 ");
+    module.extend_from_slice(&[0x00, 0xC0, 0x00, 0x00, 0x00, 0x04]);
+    let mut firmware = vec![0x00, 0x07];
+    firmware.extend_from_slice(b"6ES7 315-2EH14-0AB0 ");
+    firmware.extend_from_slice(&[0x00, 0xC0, b'V', 3, 2, 6]);
+    let modules = ModuleIdentification::decode(&szl(0x0011, 28, &[&module, &firmware])).unwrap();
+    assert_eq!(modules[0].order_number, "6ES7 315-2EH14-0AB0");
+    assert_eq!(modules[0].version(), "4");
+    assert_eq!(modules[1].index, ModuleIdentification::INDEX_FIRMWARE);
+    assert_eq!(modules[1].version(), "V3.2.6");
+
+    let record = |index: u16, data: &[u8]| {
+        let mut record = index.to_be_bytes().to_vec();
+        record.extend_from_slice(data);
+        record.resize(34, 0);
+        record
+    };
+    let mut oem = vec![0u8; 26];
+    oem[..3].copy_from_slice(b"OEM");
+    oem.extend_from_slice(&[0x00, 0x2A, 0x00, 0x00, 0x01, 0x00]);
+    let records = [
+        record(1, b"SIMATIC 300(1)"),
+        record(2, b"CPU 315-2 PN/DP"),
+        record(4, b"Original Siemens Equipment"),
+        record(5, b"S C-X4U421302009"),
+        record(9, &[0x00, 0x2A, 0xF6, 0x00, 0x00, 0x01]),
+        record(0x0A, &oem),
+        record(0x0B, b"Hall 3"),
+    ];
+    let records: Vec<&[u8]> = records.iter().map(|r| r.as_slice()).collect();
+    let component = ComponentIdentification::decode(&szl(0x001C, 34, &records)).unwrap();
+    assert_eq!(component.as_name, "SIMATIC 300(1)");
+    assert_eq!(component.module_name, "CPU 315-2 PN/DP");
+    assert_eq!(component.copyright, "Original Siemens Equipment");
+    assert_eq!(component.serial_number, "S C-X4U421302009");
+    assert_eq!(component.manufacturer_id, Some(0x2A));
+    assert_eq!(component.oem_copyright, "OEM");
+    assert_eq!(component.oem_id, Some(0x2A));
+    assert_eq!(component.oem_additional_id, Some(0x100));
+    assert_eq!(component.location, "Hall 3");
+    assert_eq!(component.plant_id, "");
+
+    assert!(ComponentIdentification::decode(&szl(0x001C, 28, &[&module])).is_err());
+}
+
+#[test]
+fn test_decode_status() {
+    let mut general = vec![0x00, 0x01, 0x00, 0xF0, 0x00, 0x10];
+    general.extend_from_slice(&187_500u32.to_be_bytes());
+    general.extend_from_slice(&12_000_000u32.to_be_bytes());
+    general.resize(40, 0);
+    let mut operator = vec![0x00, 0x03, 1, 2, 3, 4, 5, 6, 7, 8];
+    operator.resize(40, 9);
+    let capabilities =
+        CommunicationCapabilities::decode(&szl(0x0131, 40, &[&general, &operator])).unwrap();
+    assert_eq!(
+        capabilities[0],
+        CommunicationCapabilities::General {
+            max_pdu_length: 240,
+            max_connections: 16,
+            max_mpi_rate: 187_500,
+            max_bus_rate: 12_000_000,
+        }
+    );
+    assert_eq!(capabilities[1].index(), 3);
+
+    let protection = [0, 4, 0, 1, 0, 0, 0, 1, 0, 2, 0, 0, 0, 0];
+    let protection = Protection::decode(&szl(0x0232, 14, &[&protection])).unwrap();
+    assert_eq!(protection.level, 1);
+    assert_eq!(protection.mode_selector, ModeSelector::RunP);
+
+    let transition = [
+        0x43, 0x02, 0xFF, 0x48, 0, 0, 0, 0, 0, 0, 0, 0, 0x21, 0x03, 0x15, 0x08, 0x30, 0x00, 0x12,
+        0x34,
+    ];
+    let transition = ModeTransition::decode(&szl(0x0424, 20, &[&transition])).unwrap();
+    assert_eq!(transition.mode, OperatingMode::Run);
+    assert_eq!(transition.previous_mode, OperatingMode::Stop(4));
+    assert_eq!(
+        transition.time,
+        Some(DateTime::new(2021, 3, 15, 8, 30, 0, 123_000_000).unwrap())
+    );
+
+    let leds = Led::decode(&szl(0x0019, 4, &[&[0, 4, 1, 0], &[0, 1, 0, 2]])).unwrap();
+    assert_eq!(leds[0].name(), Some("RUN"));
+    assert!(leds[0].on);
+    assert_eq!(leds[1].flash, LedFlash::Slow);
+
+    let status = [
+        0, 0, 4, 0, 0, 0, 0x80, 0x01, 0x80, 0x01, 0, 0, 0, 0x03, 0, 0x11,
+    ];
+    let status = ModuleStatus::decode(&szl(0x0D91, 16, &[&status])).unwrap();
+    assert_eq!(status[0].slot(), 4);
+    assert!(status[0].fault());
+    assert!(status[0].exists());
+    assert!(!status[0].disabled());
+}
@@ -0,0 +1,418 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Imports symbol tables exported by Step7 or TIA Portal
+//!
+//! Step7 exports the symbol table as `.sdf` (quoted, comma separated), `.seq` (tab separated)
+//! or `.asc` (fixed columns). TIA Portal exports PLC tags as CSV with a header line. Addresses
+//! use the grammar of `Area`, spaces and the `%` of TIA addresses are removed first. Block
+//! symbols like `FB 1` or `DB 10` are skipped.
+//!
+//! # Examples
+//!
+//! ```
+//! use s7::tag::TagTable;
+//!
+//! let tags = TagTable::from_sdf(r#"
+//! "Motor1_Running","M      10.0","BOOL","Motor 1 is running"
+//! "Motor1_Speed","MW     12","INT","rpm"
+//! "#).unwrap();
+//!
+//! let speed = tags.get("Motor1_Speed").unwrap();
+//! assert_eq!(speed.area.to_string(), "P#M12.0 INT 1");
+//! assert_eq!(speed.comment, "rpm");
+//! ```
+
+use crate::constant::{Area, DataSizeType};
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+
+/// symbol of the symbol table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    /// the data type of the area matches `data_type` where possible, ie INT for `MW12 INT`
+    pub area: Area,
+    /// data type as written in the symbol table
+    pub data_type: String,
+    pub comment: String,
+}
+
+impl Tag {
+    /// tag for `address` like `M 10.0` or `%MW12`, `data_type` refines the area type
+    pub fn new(name: &str, address: &str, data_type: &str, comment: &str) -> Result<Tag, Error> {
+        let area = parse_address(address)?;
+        Ok(Tag {
+            name: name.trim().to_string(),
+            area: typed_area(area, data_type.trim())?,
+            data_type: data_type.trim().to_string(),
+            comment: comment.trim().to_string(),
+        })
+    }
+}
+
+/// symbols by name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TagTable {
+    tags: Vec<Tag>,
+}
+
+/// record type of a symbol in `.seq` and `.asc` exports
+const SYMBOL_RECORD: &str = "126,";
+
+impl TagTable {
+    /// imports a Step7 `.sdf` export, ie `"Start","I       0.0","BOOL","Start button"`
+    pub fn from_sdf(text: &str) -> Result<TagTable, Error> {
+        let mut table = TagTable::default();
+        for (index, line) in lines(text) {
+            match split_csv(line, ',').as_slice() {
+                [name, address, data_type, comment] => {
+                    table.import(index, name, address, data_type, comment)?
+                }
+                _ => return Err(line_error(index, "expected 4 columns")),
+            }
+        }
+        Ok(table)
+    }
+
+    /// imports a Step7 `.seq` export, ie `126,Start<TAB>I 0.0<TAB>BOOL<TAB>Start button`
+    pub fn from_seq(text: &str) -> Result<TagTable, Error> {
+        let mut table = TagTable::default();
+        for (index, line) in lines(text) {
+            let line = line.strip_prefix(SYMBOL_RECORD).unwrap_or(line);
+            let columns: Vec<&str> = line.split('\t').collect();
+            match columns.as_slice() {
+                [name, address, data_type] => table.import(index, name, address, data_type, "")?,
+                [name, address, data_type, comment] => {
+                    table.import(index, name, address, data_type, comment)?
+                }
+                _ => return Err(line_error(index, "expected 4 columns")),
+            }
+        }
+        Ok(table)
+    }
+
+    /// imports a Step7 ASCII export with fixed columns:
+    /// `126,`, 24 characters name, 12 address, 10 data type and the comment
+    pub fn from_asc(text: &str) -> Result<TagTable, Error> {
+        let mut table = TagTable::default();
+        for (index, line) in lines(text) {
+            let line = line.strip_prefix(SYMBOL_RECORD).unwrap_or(line);
+            let chars: Vec<char> = line.chars().collect();
+            if chars.len() < 36 {
+                return Err(line_error(index, "line is too short"));
+            }
+            let column = |from: usize, to: usize| -> String {
+                chars[from.min(chars.len())..to.min(chars.len())]
+                    .iter()
+                    .collect()
+            };
+            table.import(
+                index,
+                &column(0, 24),
+                &column(24, 36),
+                &column(36, 46),
+                &column(46, chars.len()),
+            )?;
+        }
+        Ok(table)
+    }
+
+    /// imports a TIA Portal PLC tag CSV export
+    ///
+    /// The columns are found by the header `Name`, `Data Type`, `Logical Address` and the
+    /// optional `Comment`, separated by `,` or `;`.
+    pub fn from_tia_csv(text: &str) -> Result<TagTable, Error> {
+        let mut lines = lines(text);
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| line_error(1, "missing header"))?;
+        let separator = if header.contains(';') { ';' } else { ',' };
+        let header = split_csv(header, separator);
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|column| column.eq_ignore_ascii_case(name))
+        };
+        let (name, data_type, address) = match (
+            column("Name"),
+            column("Data Type"),
+            column("Logical Address"),
+        ) {
+            (Some(name), Some(data_type), Some(address)) => (name, data_type, address),
+            _ => {
+                return Err(line_error(
+                    1,
+                    "expected the columns Name, Data Type and Logical Address",
+                ))
+            }
+        };
+        let comment = column("Comment");
+
+        let mut table = TagTable::default();
+        for (index, line) in lines {
+            let columns = split_csv(line, separator);
+            let get = |column: usize| columns.get(column).map_or("", |value| value.as_str());
+            if columns.len() < header.len().min(address + 1) {
+                return Err(line_error(index, "missing columns"));
+            }
+            table.import(
+                index,
+                get(name),
+                get(address),
+                get(data_type),
+                comment.map_or("", get),
+            )?;
+        }
+        Ok(table)
+    }
+
+    fn import(
+        &mut self,
+        line: usize,
+        name: &str,
+        address: &str,
+        data_type: &str,
+        comment: &str,
+    ) -> Result<(), Error> {
+        if is_block(address) {
+            return Ok(());
+        }
+        let tag = Tag::new(name, address, data_type, comment)
+            .map_err(|err| line_error(line, &err.to_string()))?;
+        self.insert(tag);
+        Ok(())
+    }
+
+    /// adds `tag`, a tag with the same name is replaced
+    pub fn insert(&mut self, tag: Tag) {
+        match self.tags.iter_mut().find(|t| t.name == tag.name) {
+            Some(existing) => *existing = tag,
+            None => self.tags.push(tag),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// area of the tag `name`
+    pub fn area(&self, name: &str) -> Result<Area, Error> {
+        self.get(name)
+            .map(|tag| tag.area)
+            .ok_or_else(|| Error::InvalidInput {
+                input: format!("unknown tag {}", name),
+            })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Tag> {
+        self.tags.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+/// non empty lines with their line number
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.trim().is_empty())
+}
+
+fn line_error(line: usize, reason: &str) -> Error {
+    Error::InvalidInput {
+        input: format!("line {}: {}", line, reason),
+    }
+}
+
+/// splits a CSV line, quotes are escaped by doubling them
+fn split_csv(line: &str, separator: char) -> Vec<String> {
+    let mut columns = Vec::new();
+    let mut column = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                column.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => columns.push(std::mem::take(&mut column)),
+            c => column.push(c),
+        }
+    }
+    columns.push(column);
+    columns
+}
+
+/// block symbols like `FB 1`, `DB 10` or `VAT 2`
+fn is_block(address: &str) -> bool {
+    let address: String = address.split_whitespace().collect();
+    let address = address.to_ascii_uppercase();
+    let number = address.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let kind = &address[..address.len() - number.len()];
+    !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit())
+        && ["OB", "FB", "FC", "SFB", "SFC", "DB", "SDB", "UDT", "VAT"].contains(&kind)
+}
+
+/// removes spaces and the `%` of TIA addresses, `%IW64:P` is the peripheral input `PIW64`
+fn parse_address(address: &str) -> Result<Area, Error> {
+    let mut address: String = address.split_whitespace().collect();
+    if let Some(tia) = address.strip_prefix('%') {
+        address = match tia.strip_suffix(":P").or_else(|| tia.strip_suffix(":p")) {
+            Some(peripheral) => format!("P{}", peripheral),
+            None => tia.to_string(),
+        };
+    }
+    address.parse()
+}
+
+/// the area with the data type of the symbol, the size has to match
+///
+/// Only types which fit a single address are supported, types like `LREAL`, `STRING`, `DTL`
+/// or UDTs are rejected.
+fn typed_area(area: Area, data_type: &str) -> Result<Area, Error> {
+    use DataSizeType::*;
+    let data_type = data_type.to_ascii_uppercase();
+    let data = match (*area, data_type.as_str()) {
+        (Bit { .. }, "BOOL") => *area,
+        (Byte { addr, len: 1 }, "CHAR") => Char { addr, len: 1 },
+        (Byte { len: 1, .. }, "BYTE")
+        | (Byte { len: 1, .. }, "USINT")
+        | (Byte { len: 1, .. }, "SINT") => *area,
+        (Word { addr, len: 1 }, "INT") => Int { addr, len: 1 },
+        (Word { len: 1, .. }, "WORD")
+        | (Word { len: 1, .. }, "UINT")
+        | (Word { len: 1, .. }, "S5TIME")
+        | (Word { len: 1, .. }, "DATE") => *area,
+        (DWord { addr, len: 1 }, "DINT") => DInt { addr, len: 1 },
+        (DWord { addr, len: 1 }, "REAL") => Real { addr, len: 1 },
+        (DWord { len: 1, .. }, "DWORD")
+        | (DWord { len: 1, .. }, "UDINT")
+        | (DWord { len: 1, .. }, "TIME")
+        | (DWord { len: 1, .. }, "TIME_OF_DAY")
+        | (DWord { len: 1, .. }, "TOD") => *area,
+        (Counter { .. }, "COUNTER") | (Timer { .. }, "TIMER") => *area,
+        (_, "BOOL")
+        | (_, "BYTE")
+        | (_, "CHAR")
+        | (_, "USINT")
+        | (_, "SINT")
+        | (_, "WORD")
+        | (_, "INT")
+        | (_, "UINT")
+        | (_, "S5TIME")
+        | (_, "DATE")
+        | (_, "DWORD")
+        | (_, "DINT")
+        | (_, "UDINT")
+        | (_, "REAL")
+        | (_, "TIME")
+        | (_, "TIME_OF_DAY")
+        | (_, "TOD")
+        | (_, "COUNTER")
+        | (_, "TIMER") => {
+            return Err(Error::InvalidInput {
+                input: format!("data type {} does not fit address {}", data_type, area),
+            })
+        }
+        _ => {
+            return Err(Error::InvalidInput {
+                input: format!("unsupported data type {} of address {}", data_type, area),
+            })
+        }
+    };
+    Ok(area.with_data(data))
+}
+
+#[test]
+fn test_import_step7() {
+    let sdf = concat!(
+        "\"Start\",\"I       0.0\",\"BOOL\",\"Start \"\"green\"\" button\"\r\n",
+        "\"Level\",\"PIW   256\",\"INT\",\"\"\r\n",
+        "\"Recipe\",\"DB     10\",\"DB     10\",\"\"\r\n",
+        "\"Pieces\",\"C       3\",\"COUNTER\",\"\"\r\n",
+        "\"Total\",\"MD     20\",\"REAL\",\"liters\"\r\n",
+        "\"Offset\",\"MB      5\",\"SINT\",\"\"\r\n",
+    );
+    let tags = TagTable::from_sdf(sdf).unwrap();
+    assert_eq!(tags.len(), 5);
+    assert_eq!(tags.get("Start").unwrap().comment, "Start \"green\" button");
+    assert_eq!(tags.area("Start").unwrap().to_string(), "I0.0");
+    assert_eq!(tags.area("Level").unwrap().to_string(), "P#PI256.0 INT 1");
+    assert_eq!(tags.area("Pieces").unwrap().to_string(), "C3");
+    assert_eq!(tags.area("Total").unwrap().to_string(), "P#M20.0 REAL 1");
+    assert_eq!(tags.area("Offset").unwrap().to_string(), "MB5");
+    assert!(tags.get("Recipe").is_none());
+    assert!(tags.area("Stop").is_err());
+
+    let seq = "126,Start\tI       0.0\tBOOL\tStart button\n126,Speed\tMW     12\tWORD\t\n";
+    let tags = TagTable::from_seq(seq).unwrap();
+    assert_eq!(tags.area("Speed").unwrap().to_string(), "MW12");
+    assert_eq!(tags.get("Start").unwrap().comment, "Start button");
+
+    let asc = format!(
+        "126,{:<24}{:<12}{:<10}{}\n126,{:<24}{:<12}{:<10}\n",
+        "Motor1_Running",
+        "M      10.1",
+        "BOOL",
+        "Motor 1 is running",
+        "Fault",
+        "Q       4.7",
+        "BOOL"
+    );
+    let tags = TagTable::from_asc(&asc).unwrap();
+    assert_eq!(tags.area("Motor1_Running").unwrap().to_string(), "M10.1");
+    assert_eq!(
+        tags.get("Motor1_Running").unwrap().comment,
+        "Motor 1 is running"
+    );
+    assert_eq!(tags.area("Fault").unwrap().to_string(), "Q4.7");
+
+    assert!(TagTable::from_sdf("\"A\",\"MW 2\",\"REAL\",\"\"").is_err());
+    assert!(TagTable::from_sdf("\"A\",\"M 2.8\",\"BOOL\",\"\"").is_err());
+    assert!(TagTable::from_sdf("\"A\",\"M 2.0\"").is_err());
+
+    for (address, data_type) in [
+        ("MD 8", "LREAL"),
+        ("MW 2", "STRING"),
+        ("MD 4", "DATE_AND_TIME"),
+        ("MB 0", "DTL"),
+        ("MW 2", "\"Motor\""),
+        ("MW 2", "UDT 5"),
+    ] {
+        match Tag::new("A", address, data_type, "") {
+            Err(Error::InvalidInput { .. }) => {}
+            other => panic!("{}: expected invalid input got {:?}", data_type, other),
+        }
+    }
+}
+
+#[test]
+fn test_import_tia_csv() {
+    let csv = "\
+Name;Path;Data Type;Logical Address;Comment;Hmi Visible;Hmi Accessible;Hmi Writeable
+Motor1_Running;Default tag table;Bool;%M10.0;Motor 1 is running;True;True;True
+Motor1_Speed;Default tag table;DInt;%MD14;\"rpm; set point\";True;True;True
+Level;Default tag table;Int;%IW64:P;;True;True;True
+";
+    let tags = TagTable::from_tia_csv(csv).unwrap();
+    assert_eq!(tags.len(), 3);
+    let speed = tags.get("Motor1_Speed").unwrap();
+    assert_eq!(speed.area.to_string(), "P#M14.0 DINT 1");
+    assert_eq!(speed.data_type, "DInt");
+    assert_eq!(speed.comment, "rpm; set point");
+    assert_eq!(tags.area("Level").unwrap().to_string(), "P#PI64.0 INT 1");
+
+    assert!(TagTable::from_tia_csv("Name,Path\nA,B\n").is_err());
+}
@@ -77,7 +77,7 @@ impl Options {
             write_timeout: config.timeout,
             port: config.port,
             address: config.address.into(), //ip:102,
-            conn_type: config.collect_mode.conn_type().clone(),
+            conn_type: *config.collect_mode.conn_type(),
             local_tsap_high: local_tsap[0],
             local_tsap_low: local_tsap[1],
             remote_tsap_high: remote_tsap[0],
@@ -135,7 +135,7 @@ impl TcpTransport {
     fn negotiate_pdu_length(&mut self) -> Result<(), Error> {
         // Set PDU Size Requested //lth
         let mut pdu_size_package = transport::PDU_NEGOTIATION_TELEGRAM.to_vec();
-        BigEndian::write_u16(pdu_size_package[23..].as_mut(), PDU_SIZE_REQUESTED as u16);
+        BigEndian::write_u16(pdu_size_package[23..].as_mut(), PDU_SIZE_REQUESTED);
 
         // Sends the connection request telegram
         let response = self.send(pdu_size_package.as_slice())?;
@@ -143,7 +143,7 @@ impl TcpTransport {
             // 20 = size of Negotiate Answer
             // Get PDU Size Negotiated
             self.options.pdu_length = BigEndian::read_u16(&response[25..]);
-            if self.options.pdu_length <= 0 {
+            if self.options.pdu_length == 0 {
                 return Err(Error::Response {
                     code: error::CLI_NEGOTIATING_PDU,
                 });
@@ -164,23 +164,23 @@ impl Transport for TcpTransport {
             Ok(s) => s,
             Err(_) => return Err(Error::Lock),
         };
-        stream.write(request)?;
+        stream.write_all(request)?;
 
         let mut data = vec![0u8; MAX_LENGTH];
         let mut length;
 
         loop {
             // Get TPKT (4 bytes)
-            stream.read(&mut data[..4])?;
+            stream.read_exact(&mut data[..4])?;
 
             // Read length, ignore transaction & protocol id (4 bytes)
             length = BigEndian::read_u16(&data[2..]);
             let length_n = length;
 
             if length_n == ISO_HEADER_SIZE {
-                stream.read(&mut data[4..7])?;
+                stream.read_exact(&mut data[4..7])?;
             } else {
-                if length_n > PDU_SIZE_REQUESTED + ISO_HEADER_SIZE || length_n < MIN_PDU_SIZE {
+                if !(MIN_PDU_SIZE..=PDU_SIZE_REQUESTED + ISO_HEADER_SIZE).contains(&length_n) {
                     return Err(Error::PduLength(length_n));
                 }
                 break;
@@ -188,11 +188,11 @@ impl Transport for TcpTransport {
         }
 
         // Skip remaining 3 COTP bytes
-        stream.read(&mut data[4..7])?;
+        stream.read_exact(&mut data[4..7])?;
         self.options.last_pdu_type = data[5]; // Stores PDU Type, we need it for later
 
         // Receives the S7 Payload
-        stream.read(&mut data[7..length as usize])?;
+        stream.read_exact(&mut data[7..length as usize])?;
         Ok(data[0..length as usize].to_vec())
     }
 
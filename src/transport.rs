@@ -10,23 +10,18 @@ use serde::{Deserialize, Serialize};
 /// The types are defined from the highest to lowest priority
 /// The basic connections are the first which would be closed
 /// if there aren't enough resources
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub enum Connection {
     /// Connect to the PLC programming console (Programmiergeräte). German for programming device.
     PG = 1,
     /// Connect to the PLC Siemens HMI panel
+    #[default]
     OP = 2,
     /// Basic connection for generic data transfer connection
     /// 14 Basic connections
     Basic = 3,
 }
 
-impl Default for Connection {
-    fn default() -> Self {
-        Self::OP
-    }
-}
-
 /// an abstract communication used by the client to send requests
 /// ## How can I implement `Transport`?
 ///
@@ -140,7 +135,11 @@ pub(crate) const SZL_FIRST_TELEGRAM: [u8; 33] = [
     0, 0,
 ]; // Index (31)];
 
-pub(crate) const MIN_SZL_FIRST_TELEGRAM: usize = 42;
+/// offset of the data of a SZL response, after the data length
+pub(crate) const SZL_DATA_OFFSET: usize = 33;
+
+/// ID, index, record length and number of records at the start of the first fragment
+pub(crate) const SZL_HEADER_SIZE: usize = 8;
 
 pub(crate) const SZL_NEXT_TELEGRAM: [u8; 33] = [
     3, 0, 0, 33, 2, 240, 128, 50, 7, 0, 0, 6, 0, 0, 12, 0, 4, 0, 1, 18, 8, 18, 68, 1,
@@ -148,24 +147,50 @@ pub(crate) const SZL_NEXT_TELEGRAM: [u8; 33] = [
     0, 0, 0, 0, 10, 0, 0, 0,
 ]; // Index (31)];
 
+/// read clock request, userdata function group time functions
+pub(crate) const GET_CLOCK_TELEGRAM: [u8; 29] = [
+    3, 0, 0, 29, 2, 240, 128, 50, 7, 0, 0, 56, 0, 0, 8, 0, 4, 0, 1, 18, 4, 17, 71, 1, 0, 10, 0, 0,
+    0,
+];
+
+/// offset of the BCD DATE_AND_TIME in a read clock response, after the century byte
+pub(crate) const GET_CLOCK_TIME: usize = 35;
+
+/// set clock request
+pub(crate) const SET_CLOCK_TELEGRAM: [u8; 39] = [
+    3, 0, 0, 39, 2, 240, 128, 50, 7, 0, 0, 137, 3, 0, 8, 0, 14, 0, 1, 18, 4, 17, 71, 2, 0, 255, 9,
+    0, 10, 0, 25, // century (30)
+    0, 0, 0, 0, 0, 0, 0, 0, // DATE_AND_TIME (31)
+];
+
+/// offset of the century byte in a set clock request, the DATE_AND_TIME follows
+pub(crate) const SET_CLOCK_CENTURY: usize = 30;
+
+/// set session password request, userdata function group security
+pub(crate) const SET_PASSWORD_TELEGRAM: [u8; 37] = [
+    3, 0, 0, 37, 2, 240, 128, 50, 7, 0, 0, 39, 0, 0, 8, 0, 12, 0, 1, 18, 4, 17, 69, 1, 0, 255, 9,
+    0, 8, // encoded password (29)
+    0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+/// offset of the encoded password in a set session password request
+pub(crate) const SET_PASSWORD_DATA: usize = 29;
+
+/// clear session password request
+pub(crate) const CLEAR_PASSWORD_TELEGRAM: [u8; 29] = [
+    3, 0, 0, 29, 2, 240, 128, 50, 7, 0, 0, 41, 0, 0, 8, 0, 4, 0, 1, 18, 4, 17, 69, 2, 0, 10, 0, 0,
+    0,
+];
+
+/// size of a userdata response up to the error code of the parameters
+pub(crate) const USERDATA_MIN_RESPONSE: usize = 29;
+
 pub(crate) const PLC_STATUS_MIN_RESPONSE: usize = 45;
 
 pub(crate) const TELEGRAM_MIN_RESPONSE: usize = 19;
 
-pub(crate) const SZL_MIN_RESPONSE: usize = 205;
-
 pub(crate) const PDU_START: u8 = 0x28; // CPU start
 pub(crate) const PDU_STOP: u8 = 0x29; // CPU stop
 
 pub(crate) const PDU_ALREADY_STARTED: u8 = 0x02; // CPU already in run mode
 pub(crate) const PDU_ALREADY_STOPPED: u8 = 0x07; // CPU already in stop mode
-
-pub(crate) struct SZLHeader {
-    pub length_header: u16,
-    pub number_of_data_record: u16,
-}
-
-pub(crate) struct S7SZL {
-    pub header: SZLHeader,
-    pub data: Vec<u8>,
-}
@@ -0,0 +1,495 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Conversions between PLC encoded values and rust types
+
+use super::constant::DataSizeType;
+use super::error::Error;
+use byteorder::{BigEndian, ByteOrder};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// a single value of an area, decoded big-endian according to its `DataSizeType`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum S7Value {
+    Bool(bool),
+    Byte(u8),
+    Char(char),
+    Word(u16),
+    Int(i16),
+    DWord(u32),
+    DInt(i32),
+    Real(f32),
+    /// counter value, BCD encoded in the PLC
+    Counter(u16),
+    /// timer value, S5TIME encoded in the PLC
+    Timer(Duration),
+}
+
+impl S7Value {
+    /// decodes `bytes` read from an area with the data type `data`
+    pub fn decode(data: &DataSizeType, bytes: &[u8]) -> Result<Vec<S7Value>, Error> {
+        if bytes.len() != data.byte_len() {
+            return Err(Error::TryFrom(
+                bytes.to_vec(),
+                format!(
+                    "S7Value.decode: expected buf size {} got {}",
+                    data.byte_len(),
+                    bytes.len()
+                ),
+            ));
+        }
+        bytes
+            .chunks(data.length() as usize)
+            .map(|b| {
+                Ok(match data {
+                    DataSizeType::Bit { .. } => S7Value::Bool(b[0] & 0x01 != 0),
+                    DataSizeType::Byte { .. } => S7Value::Byte(b[0]),
+                    DataSizeType::Char { .. } => S7Value::Char(b[0] as char),
+                    DataSizeType::Word { .. } => S7Value::Word(BigEndian::read_u16(b)),
+                    DataSizeType::Int { .. } => S7Value::Int(BigEndian::read_i16(b)),
+                    DataSizeType::DWord { .. } => S7Value::DWord(BigEndian::read_u32(b)),
+                    DataSizeType::DInt { .. } => S7Value::DInt(BigEndian::read_i32(b)),
+                    DataSizeType::Real { .. } => S7Value::Real(BigEndian::read_f32(b)),
+                    DataSizeType::Counter { .. } => {
+                        S7Value::Counter(counter_from_bytes([b[0], b[1]])?)
+                    }
+                    DataSizeType::Timer { .. } => S7Value::Timer(s5time_from_bytes([b[0], b[1]])?),
+                })
+            })
+            .collect()
+    }
+
+    /// encodes `values` for an area with the data type `data`,
+    /// the number and kind of the values have to match the area
+    pub fn encode(data: &DataSizeType, values: &[S7Value]) -> Result<Vec<u8>, Error> {
+        if values.len() != data.len() as usize {
+            return Err(Error::InvalidInput {
+                input: format!(
+                    "{:?}: expected {} values got {}",
+                    data,
+                    data.len(),
+                    values.len()
+                ),
+            });
+        }
+        let mut bytes = Vec::with_capacity(data.byte_len());
+        for value in values {
+            match (data, value) {
+                (DataSizeType::Bit { .. }, S7Value::Bool(v)) => bytes.push(*v as u8),
+                (DataSizeType::Byte { .. }, S7Value::Byte(v)) => bytes.push(*v),
+                (DataSizeType::Char { .. }, S7Value::Char(v)) if (*v as u32) <= 0xFF => {
+                    bytes.push(*v as u8)
+                }
+                (DataSizeType::Word { .. }, S7Value::Word(v)) => {
+                    bytes.extend_from_slice(&v.to_be_bytes())
+                }
+                (DataSizeType::Int { .. }, S7Value::Int(v)) => {
+                    bytes.extend_from_slice(&v.to_be_bytes())
+                }
+                (DataSizeType::DWord { .. }, S7Value::DWord(v)) => {
+                    bytes.extend_from_slice(&v.to_be_bytes())
+                }
+                (DataSizeType::DInt { .. }, S7Value::DInt(v)) => {
+                    bytes.extend_from_slice(&v.to_be_bytes())
+                }
+                (DataSizeType::Real { .. }, S7Value::Real(v)) => {
+                    bytes.extend_from_slice(&v.to_be_bytes())
+                }
+                (DataSizeType::Counter { .. }, S7Value::Counter(v)) => {
+                    bytes.extend_from_slice(&counter_to_bytes(*v)?)
+                }
+                (DataSizeType::Timer { .. }, S7Value::Timer(v)) => {
+                    bytes.extend_from_slice(&s5time_to_bytes(*v)?)
+                }
+                _ => {
+                    return Err(Error::InvalidInput {
+                        input: format!("{:?}: value {:?} does not match", data, value),
+                    })
+                }
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+/// S5TIME time bases in milliseconds, selected by bits 12 and 13
+const S5TIME_BASES: [u64; 4] = [10, 100, 1_000, 10_000];
+
+/// decodes a 3 digit BCD value, ie `0x0123` is 123
+fn bcd_to_u16(value: u16) -> Result<u16, Error> {
+    let mut result = 0;
+    for shift in [8, 4, 0] {
+        let digit = (value >> shift) & 0x0F;
+        if digit > 9 {
+            return Err(Error::TryFrom(
+                value.to_be_bytes().to_vec(),
+                format!("invalid BCD digit {:X}", digit),
+            ));
+        }
+        result = result * 10 + digit;
+    }
+    Ok(result)
+}
+
+/// encodes a value up to 999 as 3 digit BCD
+fn u16_to_bcd(value: u16) -> u16 {
+    ((value / 100) << 8) | ((value / 10 % 10) << 4) | (value % 10)
+}
+
+/// decodes a counter value, ie the BCD word `[0x01, 0x23]` is 123
+pub fn counter_from_bytes(bytes: [u8; 2]) -> Result<u16, Error> {
+    bcd_to_u16(u16::from_be_bytes(bytes) & 0x0FFF)
+}
+
+/// encodes a counter value as BCD word, valid counter values are 0 to 999
+pub fn counter_to_bytes(value: u16) -> Result<[u8; 2], Error> {
+    if value > 999 {
+        return Err(Error::InvalidInput {
+            input: format!("counter value {} out of range 0..=999", value),
+        });
+    }
+    Ok(u16_to_bcd(value).to_be_bytes())
+}
+
+/// decodes a S5TIME word, bits 12 and 13 are the time base and bits 0 to 11 the BCD value
+pub fn s5time_from_bytes(bytes: [u8; 2]) -> Result<Duration, Error> {
+    let word = u16::from_be_bytes(bytes);
+    let base = S5TIME_BASES[((word >> 12) & 0x03) as usize];
+    let value = bcd_to_u16(word & 0x0FFF)?;
+    Ok(Duration::from_millis(value as u64 * base))
+}
+
+/// encodes a duration as S5TIME word with the finest time base that can hold it
+///
+/// The duration is truncated to a multiple of the time base, the max is 9990 seconds.
+pub fn s5time_to_bytes(duration: Duration) -> Result<[u8; 2], Error> {
+    let millis = duration.as_millis();
+    for (i, base) in S5TIME_BASES.iter().enumerate() {
+        let value = millis / *base as u128;
+        if value <= 999 {
+            return Ok((((i as u16) << 12) | u16_to_bcd(value as u16)).to_be_bytes());
+        }
+    }
+    Err(Error::InvalidInput {
+        input: format!("duration {:?} exceeds S5TIME max of 9990s", duration),
+    })
+}
+
+/// days between 1970-01-01 and 1990-01-01, the epoch of the S7 DATE type
+const DAYS_1970_TO_1990: i64 = 7305;
+
+/// decodes a 2 digit BCD byte, ie `0x59` is 59
+pub(crate) fn bcd_byte(value: u8) -> Result<u8, Error> {
+    Ok(bcd_to_u16(value as u16)? as u8)
+}
+
+/// encodes a value up to 99 as 2 digit BCD byte
+pub(crate) fn to_bcd_byte(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+/// days since 1970-01-01 of a proleptic gregorian date
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// proleptic gregorian date of the days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u8;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u8;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// calendar date and time of day as used by the S7 DATE, DATE_AND_TIME and DTL types
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+}
+
+impl DateTime {
+    pub fn new(
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+        nanosecond: u32,
+    ) -> Result<DateTime, Error> {
+        let date_time = DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            nanosecond,
+        };
+        let (_, m, d) = civil_from_days(date_time.days());
+        if month == 0
+            || m != month
+            || d != day
+            || hour > 23
+            || minute > 59
+            || second > 59
+            || nanosecond > 999_999_999
+        {
+            return Err(Error::InvalidInput {
+                input: format!("invalid date and time {:?}", date_time),
+            });
+        }
+        Ok(date_time)
+    }
+
+    /// days since 1970-01-01
+    fn days(&self) -> i64 {
+        days_from_civil(self.year as i64, self.month, self.day)
+    }
+
+    /// day of the week as counted by the PLC, 1 is sunday and 7 is saturday
+    pub fn weekday(&self) -> u8 {
+        ((self.days() + 4).rem_euclid(7) + 1) as u8
+    }
+
+    /// decodes a S7 DATE, the days since 1990-01-01
+    pub fn from_date_bytes(bytes: [u8; 2]) -> DateTime {
+        let (year, month, day) =
+            civil_from_days(DAYS_1970_TO_1990 + u16::from_be_bytes(bytes) as i64);
+        DateTime {
+            year: year as u16,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            nanosecond: 0,
+        }
+    }
+
+    /// encodes the date as S7 DATE, the time of day is dropped
+    pub fn to_date_bytes(&self) -> Result<[u8; 2], Error> {
+        let days = self.days() - DAYS_1970_TO_1990;
+        if !(0..=u16::MAX as i64).contains(&days) {
+            return Err(Error::InvalidInput {
+                input: format!("{:?} out of the DATE range", self),
+            });
+        }
+        Ok((days as u16).to_be_bytes())
+    }
+
+    /// decodes a BCD encoded S7 DATE_AND_TIME, years 90 to 99 are 1990 to 1999,
+    /// 00 to 89 are 2000 to 2089
+    pub fn from_bcd_bytes(bytes: [u8; 8]) -> Result<DateTime, Error> {
+        let year = bcd_byte(bytes[0])? as u16;
+        let millis = bcd_to_u16(((bytes[6] as u16) << 4) | (bytes[7] >> 4) as u16)?;
+        DateTime::new(
+            if year >= 90 { 1900 + year } else { 2000 + year },
+            bcd_byte(bytes[1])?,
+            bcd_byte(bytes[2])?,
+            bcd_byte(bytes[3])?,
+            bcd_byte(bytes[4])?,
+            bcd_byte(bytes[5])?,
+            millis as u32 * 1_000_000,
+        )
+    }
+
+    /// encodes the date as BCD S7 DATE_AND_TIME, truncated to milliseconds
+    pub fn to_bcd_bytes(&self) -> Result<[u8; 8], Error> {
+        if !(1990..=2089).contains(&self.year) {
+            return Err(Error::InvalidInput {
+                input: format!("{:?} out of the DATE_AND_TIME range", self),
+            });
+        }
+        let millis = u16_to_bcd((self.nanosecond / 1_000_000) as u16);
+        Ok([
+            to_bcd_byte((self.year % 100) as u8),
+            to_bcd_byte(self.month),
+            to_bcd_byte(self.day),
+            to_bcd_byte(self.hour),
+            to_bcd_byte(self.minute),
+            to_bcd_byte(self.second),
+            (millis >> 4) as u8,
+            ((millis as u8) << 4) | self.weekday(),
+        ])
+    }
+
+    /// decodes a S7 DTL
+    pub fn from_dtl_bytes(bytes: [u8; 12]) -> Result<DateTime, Error> {
+        DateTime::new(
+            BigEndian::read_u16(&bytes[0..]),
+            bytes[2],
+            bytes[3],
+            bytes[5],
+            bytes[6],
+            bytes[7],
+            BigEndian::read_u32(&bytes[8..]),
+        )
+    }
+
+    /// encodes the date as S7 DTL
+    pub fn to_dtl_bytes(&self) -> [u8; 12] {
+        let mut bytes = [0u8; 12];
+        BigEndian::write_u16(&mut bytes[0..], self.year);
+        bytes[2] = self.month;
+        bytes[3] = self.day;
+        bytes[4] = self.weekday();
+        bytes[5] = self.hour;
+        bytes[6] = self.minute;
+        bytes[7] = self.second;
+        BigEndian::write_u32(&mut bytes[8..], self.nanosecond);
+        bytes
+    }
+
+    /// UTC date and time of `time`, times before 1970 are rejected
+    pub fn from_system_time(time: SystemTime) -> Result<DateTime, Error> {
+        let since_epoch = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| Error::InvalidInput {
+                input: format!("{:?} before 1970: {}", time, err),
+            })?;
+        let seconds = since_epoch.as_secs();
+        let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+        let seconds_of_day = seconds % 86_400;
+        DateTime::new(
+            year as u16,
+            month,
+            day,
+            (seconds_of_day / 3600) as u8,
+            (seconds_of_day / 60 % 60) as u8,
+            (seconds_of_day % 60) as u8,
+            since_epoch.subsec_nanos(),
+        )
+    }
+}
+
+#[test]
+fn test_counter() {
+    assert_eq!(counter_from_bytes([0x01, 0x23]).unwrap(), 123);
+    assert_eq!(counter_from_bytes([0x09, 0x99]).unwrap(), 999);
+    assert!(counter_from_bytes([0x00, 0x1A]).is_err());
+    assert_eq!(counter_to_bytes(456).unwrap(), [0x04, 0x56]);
+    assert!(counter_to_bytes(1000).is_err());
+}
+
+#[test]
+fn test_s5time() {
+    assert_eq!(
+        s5time_from_bytes([0x00, 0x50]).unwrap(),
+        Duration::from_millis(500)
+    );
+    assert_eq!(
+        s5time_from_bytes([0x21, 0x27]).unwrap(),
+        Duration::from_secs(127)
+    );
+    assert_eq!(
+        s5time_from_bytes([0x39, 0x99]).unwrap(),
+        Duration::from_secs(9990)
+    );
+    assert!(s5time_from_bytes([0x00, 0xA0]).is_err());
+
+    assert_eq!(
+        s5time_to_bytes(Duration::from_millis(500)).unwrap(),
+        [0x00, 0x50]
+    );
+    assert_eq!(
+        s5time_to_bytes(Duration::from_secs(127)).unwrap(),
+        [0x21, 0x27]
+    );
+    assert_eq!(
+        s5time_to_bytes(Duration::from_secs(2000)).unwrap(),
+        [0x32, 0x00]
+    );
+    assert!(s5time_to_bytes(Duration::from_secs(10_000)).is_err());
+}
+
+#[test]
+fn test_s7_value() {
+    let data = DataSizeType::Int { addr: 0, len: 2 };
+    let values = S7Value::decode(&data, &[0xFF, 0xFE, 0x01, 0x00]).unwrap();
+    assert_eq!(values, vec![S7Value::Int(-2), S7Value::Int(256)]);
+    assert_eq!(
+        S7Value::encode(&data, &values).unwrap(),
+        vec![0xFF, 0xFE, 0x01, 0x00]
+    );
+
+    let data = DataSizeType::Real { addr: 0, len: 1 };
+    let values = S7Value::decode(&data, &[66, 86, 0, 0]).unwrap();
+    assert_eq!(values, vec![S7Value::Real(53.5)]);
+
+    let data = DataSizeType::Timer { addr: 0, len: 1 };
+    let values = S7Value::decode(&data, &[0x21, 0x27]).unwrap();
+    assert_eq!(values, vec![S7Value::Timer(Duration::from_secs(127))]);
+
+    let data = DataSizeType::Char { addr: 0, len: 2 };
+    let values = S7Value::decode(&data, b"S7").unwrap();
+    assert_eq!(values, vec![S7Value::Char('S'), S7Value::Char('7')]);
+
+    // wrong kind, count or size
+    assert!(S7Value::encode(&data, &[S7Value::Char('S'), S7Value::Byte(1)]).is_err());
+    assert!(S7Value::encode(&data, &[S7Value::Char('S')]).is_err());
+    assert!(S7Value::encode(&data, &[S7Value::Char('S'), S7Value::Char('€')]).is_err());
+    assert!(S7Value::decode(&data, b"S").is_err());
+}
+
+#[test]
+fn test_date_time() {
+    let dt = DateTime::new(2023, 5, 17, 13, 45, 9, 123_000_000).unwrap();
+    // 2023-05-17 was a wednesday
+    assert_eq!(dt.weekday(), 4);
+    let bcd = dt.to_bcd_bytes().unwrap();
+    assert_eq!(bcd, [0x23, 0x05, 0x17, 0x13, 0x45, 0x09, 0x12, 0x34]);
+    assert_eq!(DateTime::from_bcd_bytes(bcd).unwrap(), dt);
+    assert_eq!(DateTime::from_dtl_bytes(dt.to_dtl_bytes()).unwrap(), dt);
+
+    let dt = DateTime::from_bcd_bytes([0x95, 0x12, 0x31, 0, 0, 0, 0, 0x01]).unwrap();
+    assert_eq!((dt.year, dt.month, dt.day), (1995, 12, 31));
+
+    assert_eq!(DateTime::from_date_bytes([0, 0]).year, 1990);
+    let date = DateTime::from_date_bytes([0x2D, 0x5D]);
+    assert_eq!((date.year, date.month, date.day), (2021, 10, 18));
+    assert_eq!(date.to_date_bytes().unwrap(), [0x2D, 0x5D]);
+
+    assert!(DateTime::new(2023, 2, 29, 0, 0, 0, 0).is_err());
+    assert!(DateTime::new(2024, 2, 29, 0, 0, 0, 0).is_ok());
+    assert!(DateTime::new(2024, 13, 1, 0, 0, 0, 0).is_err());
+    assert!(DateTime::from_bcd_bytes([0x23, 0x1A, 0x01, 0, 0, 0, 0, 0]).is_err());
+    assert!(DateTime::new(2090, 1, 1, 0, 0, 0, 0)
+        .unwrap()
+        .to_bcd_bytes()
+        .is_err());
+}
+
+#[test]
+fn test_from_system_time() {
+    let time = UNIX_EPOCH + Duration::new(1_712_345_678, 250_000_000);
+    assert_eq!(
+        DateTime::from_system_time(time).unwrap(),
+        DateTime::new(2024, 4, 5, 19, 34, 38, 250_000_000).unwrap()
+    );
+    assert!(DateTime::from_system_time(UNIX_EPOCH - Duration::from_secs(1)).is_err());
+}